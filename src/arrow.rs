@@ -0,0 +1,197 @@
+//Final Project - Thomas Ivanov
+
+use std::error::Error;
+use std::io::{Read, Seek, Write};
+use std::sync::Arc;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Date32Type, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type};
+use arrow_array::{Array, ArrayRef, Date32Array, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_ipc::reader::FileReader;
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema};
+use chrono::NaiveDate;
+
+use crate::datastore::DataRow;
+use crate::logic::Lang;
+use crate::messages::{self, Msg};
+use crate::schema::{Kind, SCHEMA};
+
+/// Rows written in each record batch of a file.
+pub const BATCH_SIZE: usize = 10_000;
+
+/// The Arrow type of each kind of stored column.
+fn data_type(kind: Kind) -> DataType {
+    match kind {
+        Kind::Integer => DataType::Int64,
+        Kind::Decimal => DataType::Float64,
+        Kind::Text => DataType::Utf8,
+        Kind::Date => DataType::Date32,
+    }
+}
+
+/// The Arrow schema of the stored columns, typed from `schema::SCHEMA`: `int64` counts, a `float64` rate, a
+/// `date32` date and `utf8` names. Columns that can be empty are nullable.
+pub fn schema() -> Schema {
+    Schema::new(SCHEMA.iter().map(|column| Field::new(column.name, data_type(column.kind), !column.required)).collect::<Vec<Field>>())
+}
+
+/// Writes rows as an Arrow IPC file (also known as Feather version 2), which pandas, polars and pyarrow read
+/// with their column types.
+///
+/// # Examples
+///
+/// ```
+/// write(&data, fs::File::create("datastore.arrow")?)?;
+/// ```
+/// ```
+/// >>> polars.read_ipc("datastore.arrow")
+/// >>> pandas.read_feather("datastore.arrow")
+/// ```
+pub fn write<W: Write>(rows: &[DataRow], writer: W) -> Result<(), Box<dyn Error>> {
+    let schema = Arc::new(schema());
+    let mut writer = FileWriter::try_new(writer, &schema)?;
+    for batch in rows.chunks(BATCH_SIZE) {
+        let cells: Vec<Vec<String>> = batch.iter().map(|row| row.public_vec()).collect();
+        let columns: Vec<ArrayRef> = SCHEMA.iter().enumerate().map(|(c, column)| {
+            let values = cells.iter().map(|row| row[c].clone());
+            let array: ArrayRef = match column.kind {
+                Kind::Integer => Arc::new(values.map(|v| v.parse::<i64>().ok()).collect::<Int64Array>()),
+                Kind::Decimal => Arc::new(values.map(|v| v.parse::<f64>().ok()).collect::<Float64Array>()),
+                Kind::Text => Arc::new(values.map(Some).collect::<StringArray>()),
+                Kind::Date => Arc::new(values.map(|v| NaiveDate::parse_from_str(&v, "%Y-%m-%d").ok().map(Date32Type::from_naive_date)).collect::<Date32Array>()),
+            };
+            array
+        }).collect();
+        writer.write(&RecordBatch::try_new(schema.clone(), columns)?)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Reads the rows of an Arrow IPC file, such as one written by `write`. Each row holds the text of a value
+/// for every column of `schema::SCHEMA`, in order (empty for a null), so it can be checked and read like a
+/// line of a CSV file.
+///
+/// Columns are found by name, so the file can have other columns or another order. Besides the types written
+/// by `write`, smaller integers and floats and the other string types that analytics tools write are read.
+pub fn read<R: Read + Seek>(reader: R, lang: &Lang) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+    let reader = FileReader::try_new(reader, None)?;
+    let schema = reader.schema();
+    let mut positions: Vec<usize> = Vec::new();
+    for column in SCHEMA.iter() {
+        match schema.index_of(column.name) {
+            Ok(i) => positions.push(i),
+            Err(_) => return Err(messages::fill(lang, Msg::ArrowColumnMissing, &[&column.name]).into()),
+        }
+    }
+
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for batch in reader {
+        let batch = batch?;
+        let columns: Vec<&ArrayRef> = positions.iter().map(|i| batch.column(*i)).collect();
+        for r in 0..batch.num_rows() {
+            let mut row: Vec<String> = Vec::new();
+            for (column, array) in SCHEMA.iter().zip(columns.iter()) {
+                match cell(array.as_ref(), r) {
+                    Some(value) => row.push(value),
+                    None => return Err(messages::fill(lang, Msg::ArrowColumnType, &[&column.name, array.data_type()]).into()),
+                }
+            }
+            rows.push(row);
+        }
+    }
+    Ok(rows)
+}
+
+/// The text of a value, or `None` if the array's type can't be read.
+fn cell(array: &dyn Array, i: usize) -> Option<String> {
+    let value = match array.data_type() {
+        _ if array.is_null(i) => String::new(),
+        DataType::Int64 => array.as_primitive::<Int64Type>().value(i).to_string(),
+        DataType::Int32 => array.as_primitive::<Int32Type>().value(i).to_string(),
+        DataType::Int16 => array.as_primitive::<Int16Type>().value(i).to_string(),
+        DataType::Float64 => array.as_primitive::<Float64Type>().value(i).to_string(),
+        DataType::Float32 => array.as_primitive::<Float32Type>().value(i).to_string(),
+        DataType::Utf8 => array.as_string::<i32>().value(i).to_string(),
+        DataType::LargeUtf8 => array.as_string::<i64>().value(i).to_string(),
+        DataType::Utf8View => array.as_string_view().value(i).to_string(),
+        DataType::Date32 => array.as_primitive::<Date32Type>().value_as_date(i)?.format("%Y-%m-%d").to_string(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    use arrow_array::{Array, ArrayRef, Date32Array, Float64Array, Int64Array, RecordBatch, StringArray};
+    use arrow_ipc::writer::FileWriter;
+    use arrow_schema::{DataType, Field, Schema};
+
+    use crate::arrow;
+    use crate::datastore::DataRow;
+    use crate::logic::Lang;
+    use crate::schema::{self, Broken};
+
+    /// An Arrow file holding one batch of the given columns, as another tool would write it.
+    fn file(fields: Vec<Field>, columns: Vec<ArrayRef>) -> Cursor<Vec<u8>> {
+        let schema = Arc::new(Schema::new(fields));
+        let mut file = Cursor::new(Vec::new());
+        let mut writer = FileWriter::try_new(&mut file, &schema).unwrap();
+        writer.write(&RecordBatch::try_new(schema.clone(), columns).unwrap()).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+        file.set_position(0);
+        file
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = arrow::schema();
+        assert_eq!(schema.field_with_name("date").unwrap().data_type(), &DataType::Date32);
+        assert_eq!(schema.field_with_name("ratetotal").unwrap().data_type(), &DataType::Float64);
+        assert!(schema.field_with_name("numdeaths").unwrap().is_nullable() && !schema.field_with_name("numconf").unwrap().is_nullable());
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let rows = vec![DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "1", "10", "10", "0.1"]),
+                        DataRow::new(vec!["24", "Quebec", "Québec", "2020-03-02", "1234", "0", "0", "1234", "-2", "12.25"])];
+        let mut file = Cursor::new(Vec::new());
+        arrow::write(&rows, &mut file).unwrap();
+        file.set_position(0);
+        let read = arrow::read(file, &Lang::EN).unwrap();
+        assert_eq!(read, rows.iter().map(|r| r.public_vec()).collect::<Vec<Vec<String>>>());
+    }
+
+    #[test]
+    fn test_read_nulls() {
+        //Tools such as pandas mark every column nullable, and nulls are read as empty values
+        let fields = arrow::schema().fields().iter().map(|f| f.as_ref().clone().with_nullable(true)).collect();
+        let count = |values: Vec<Option<i64>>| Arc::new(Int64Array::from(values)) as ArrayRef;
+        let text = || Arc::new(StringArray::from(vec!["Ontario", "Ontario"])) as ArrayRef;
+        let columns = vec![count(vec![Some(35), Some(35)]), text(), text(), Arc::new(Date32Array::from(vec![18322, 18323])),
+                           count(vec![Some(10), Some(12)]), count(vec![Some(0), None]), count(vec![None, Some(1)]),
+                           count(vec![Some(10), Some(12)]), count(vec![Some(10), Some(2)]), Arc::new(Float64Array::from(vec![None, Some(0.08)]))];
+        assert_eq!(columns.iter().map(|c| c.null_count()).collect::<Vec<usize>>(), vec![0, 0, 0, 0, 0, 1, 1, 0, 0, 1]);
+        let read = arrow::read(file(fields, columns), &Lang::EN).unwrap();
+        assert_eq!(read[0], ["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "", "10", "10", ""]);
+        assert_eq!(read[1], ["35", "Ontario", "Ontario", "2020-03-02", "12", "", "1", "12", "2", "0.08"]);
+
+        //A null is only accepted where the column can be empty
+        let cells: Vec<Vec<&str>> = read.iter().map(|row| row.iter().map(|f| f.as_str()).collect()).collect();
+        assert!(schema::check_fields(&cells[0]).is_ok());
+        let error = schema::check_fields(&cells[1]).unwrap_err();
+        assert_eq!((error.column, error.broken), ("numprob", Broken::Missing));
+    }
+
+    #[test]
+    fn test_read_missing_column() {
+        //A file without every stored column can't be read
+        let file = file(vec![Field::new("pruid", DataType::Int64, false)], vec![Arc::new(Int64Array::from(vec![35])) as ArrayRef]);
+        assert_eq!(arrow::read(file, &Lang::EN).unwrap_err().to_string(), "The Arrow file has no column prname");
+    }
+}
@@ -0,0 +1,202 @@
+//Final Project - Thomas Ivanov
+
+use std::collections::BTreeSet;
+
+use crate::datastore::DataRow;
+use crate::index::Index;
+use crate::logic::{self, Config, Lang};
+use crate::merge::Key;
+use crate::messages::{self, Msg};
+use crate::ops;
+use crate::rates::{Population, Rates};
+
+/// A command of a batch edit file, such as `set 35 2020-05-01 numtoday=412`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Sets fields of the row with this `pruid` and `date`.
+    Set(Key, Vec<(String, String)>),
+    /// Deletes every row matching all of these query conditions (as in `logic::query`).
+    Delete(Vec<String>),
+    /// Adds a row with this `pruid` and `date`, with these fields set and the other counts at 0.
+    Insert(Key, Vec<(String, String)>),
+}
+
+const SET_USAGE: &str = "set <pruid> <date> <column>=<value> ...";
+const DELETE_USAGE: &str = "delete <column><op><value> ...";
+const INSERT_USAGE: &str = "insert <pruid> <date> <column>=<value> ...";
+
+/// Reads the commands of a batch edit file, one per line, along with their line numbers.
+///
+/// Blank lines and lines starting with `#` are skipped. Words are separated by spaces, and a value with
+/// spaces in it can be put in double quotes (`set 12 2020-05-01 prname="Nova Scotia"`). Returns the first
+/// line that isn't a valid command, described in the given language.
+///
+/// # Examples
+///
+/// ```
+/// let commands = parse("set 35 2020-05-01 numtoday=412\ndelete pruid=99 date<2020-03-01\n", &Lang::EN)?;
+/// ```
+pub fn parse(text: &str, lang: &Lang) -> Result<Vec<(usize, Command)>, String> {
+    let mut commands: Vec<(usize, Command)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let words = split_words(line);
+        let syntax = |usage: &str| messages::fill(lang, Msg::BatchSyntax, &[&(i + 1), &usage]);
+        let command = match words[0].to_lowercase().as_str() {
+            "set" => {
+                let (key, fields) = key_and_fields(&words[1..]).ok_or_else(|| syntax(SET_USAGE))?;
+                Command::Set(key, fields)
+            }
+            "insert" => {
+                let (key, fields) = key_and_fields(&words[1..]).ok_or_else(|| syntax(INSERT_USAGE))?;
+                Command::Insert(key, fields)
+            }
+            "delete" if words.len() > 1 && words[1..].iter().all(|w| logic::split_condition(w).is_some()) => Command::Delete(words[1..].to_vec()),
+            "delete" => return Err(syntax(DELETE_USAGE)),
+            _ => return Err(messages::fill(lang, Msg::BatchCommandUnknown, &[&(i + 1), &words[0]])),
+        };
+        commands.push((i + 1, command));
+    }
+    Ok(commands)
+}
+
+/// Splits a line into words at spaces, keeping the spaces inside double quotes and dropping the quotes.
+fn split_words(line: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Reads a `pruid` and `date` followed by `column=value` words (at least one for `set`, any number for `insert`).
+fn key_and_fields(words: &[String]) -> Option<(Key, Vec<(String, String)>)> {
+    if words.len() < 2 {
+        return None;
+    }
+    let pruid = words[0].parse::<isize>().ok()?;
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for word in words[2..].iter() {
+        let (column, value) = word.split_once('=')?;
+        fields.push((column.trim().to_string(), value.trim().to_string()));
+    }
+    Some(((pruid, words[1].clone()), fields))
+}
+
+/// Runs batch commands against a table in order, keeping the indexes and the set of locally edited rows in step.
+///
+/// Every value goes through the same checks as the interactive editor (`ops::set_field`). Stops at the first
+/// command that fails, returning it described in the configured language, so callers that want all or
+/// nothing should run the commands on a copy of the table.
+pub fn run(commands: &[(usize, Command)], config: &Config, population: &Population,
+           data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>) -> Result<(), String> {
+    for (line, command) in commands.iter() {
+        let failed = |e: &dyn std::fmt::Display| config.fill(Msg::BatchLineError, &[line, e]);
+        match command {
+            Command::Set(key, fields) => {
+                let pos = ops::find_row(index, key).ok_or_else(|| config.fill(Msg::BatchRowMissing, &[line, &key.0, &key.1]))?;
+                for (column, value) in fields.iter() {
+                    ops::edit_row(data, index, edited, pos, column, value).map_err(|e| failed(&e.describe(&config.language)))?;
+                }
+            }
+            Command::Delete(conditions) => {
+                let rates = Rates::new(data, population);
+                let matches = logic::query(conditions.join(","), config, data, index, &rates).map_err(|e| failed(&e))?;
+                ops::delete_rows(data, index, edited, &matches).map_err(|e| failed(&e.describe(&config.language)))?;
+            }
+            Command::Insert(key, fields) => {
+                if ops::find_row(index, key).is_some() {
+                    return Err(config.fill(Msg::BatchRowExists, &[line, &key.0, &key.1]));
+                }
+                let key_fields = vec![(String::from("pruid"), key.0.to_string()), (String::from("date"), key.1.clone())];
+                let row = ops::new_row(&[key_fields, fields.clone()].concat()).map_err(|e| failed(&e.describe(&config.language)))?;
+                ops::insert_row(data, index, edited, row);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use crate::batch::{self, Command};
+    use crate::datastore::DataRow;
+    use crate::index::Index;
+    use crate::logic::{Config, Lang};
+    use crate::rates::Population;
+
+    fn ontario() -> Vec<DataRow> {
+        vec![DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "1", "10", "10", "0.1"]),
+             DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-02", "12", "0", "1", "12", "5", "0.1"])]
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = "# corrections\nset 35 2020-03-02 numtoday=2 prname=\"Nova Scotia\"\n\ndelete pruid=35 date<2020-03-02\ninsert 35 2020-03-03 numconf=14 numtotal=14\n";
+        let commands = batch::parse(text, &Lang::EN).unwrap();
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0], (2, Command::Set((35, String::from("2020-03-02")),
+                                    vec![(String::from("numtoday"), String::from("2")), (String::from("prname"), String::from("Nova Scotia"))])));
+        assert_eq!(commands[1], (4, Command::Delete(vec![String::from("pruid=35"), String::from("date<2020-03-02")])));
+        assert_eq!(commands[2], (5, Command::Insert((35, String::from("2020-03-03")),
+                                    vec![(String::from("numconf"), String::from("14")), (String::from("numtotal"), String::from("14"))])));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(batch::parse("set 35\n", &Lang::EN), Err(String::from("Line 1: expected set <pruid> <date> <column>=<value> ...")));
+        assert!(batch::parse("update 35 2020-03-01\n", &Lang::EN).is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let text = "set 35 2020-03-02 numtoday=2 prname=\"Nova Scotia\"\ndelete pruid=35 date<2020-03-02\ninsert 35 2020-03-03 numconf=14 numtotal=14\n";
+        let commands = batch::parse(text, &Lang::EN).unwrap();
+        let mut data = ontario();
+        let mut index = Index::new(&data);
+        let mut edited = BTreeSet::new();
+        batch::run(&commands, &Config::default(), &Population::default(), &mut data, &mut index, &mut edited).unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!((data[0].pruid, data[0].date.as_str(), data[0].numtoday), (12, "2020-03-02", 2));
+        assert_eq!((data[1].pruid, data[1].date.as_str(), data[1].numconf, data[1].prname.as_str()), (35, "2020-03-03", 14, "Ontario"));
+        assert_eq!(index, Index::new(&data));
+    }
+
+    #[test]
+    fn test_run_errors() {
+        let config = Config::default();
+        let mut data = ontario();
+        let mut index = Index::new(&data);
+        let mut edited = BTreeSet::new();
+
+        //A refused value, a misspelled column or a missing or existing row names its line
+        let refused = [("set 35 2020-03-02 numconf=lots\n", "Line 1: numconf: \"lots\" is not a whole number"),
+                       ("\nset 35 2020-03-05 numconf=1\n", "Line 2: there is no row for 35 2020-03-05"),
+                       ("insert 35 2020-03-01 numconf=1\n", "Line 1: there is already a row for 35 2020-03-01"),
+                       ("# typo\nset 35 2020-03-01 numdaeths=3\n", "Line 2: There is no column \"numdaeths\" (the columns are pruid, prname, prname_fr, date, \
+                                                                  numconf, numprob, numdeaths, numtotal, numtoday, ratetotal, or 1-10)")];
+        for (text, error) in refused {
+            let commands = batch::parse(text, &Lang::EN).unwrap();
+            assert_eq!(batch::run(&commands, &config, &Population::default(), &mut data, &mut index, &mut edited), Err(String::from(error)));
+        }
+        assert_eq!(data, ontario());
+    }
+}
@@ -0,0 +1,162 @@
+//Final Project - Thomas Ivanov
+
+use std::collections::BTreeSet;
+
+/// Block characters used for sparklines, from lowest to highest.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Markers used to tell the series of a line chart apart, in the order the series are given.
+const MARKERS: [char; 6] = ['●', '○', '×', '+', '*', '◆'];
+
+/// The kind of chart drawn by `chart`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChartKind {
+    Line,
+    Bar,
+}
+
+/// One named series of (date, value) points to be charted, e.g. `numtoday` for one province.
+#[derive(Clone, Debug)]
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(String, f64)>,
+}
+
+/// Returns a one-line sparkline of the values, scaled between their minimum and maximum.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(sparkline(&[0.0, 7.0, 14.0]), "▁▅█");
+/// ```
+pub fn sparkline(values: &[f64]) -> String {
+    let (min, max) = bounds(values.iter().copied());
+    values.iter()
+            .map(|v| if max > min { SPARKS[((v - min) / (max - min) * 7.0).round() as usize] } else { SPARKS[0] })
+            .collect()
+}
+
+/// Draws a line or bar chart of one or more series over date, `height` rows tall and at most `width` columns wide.
+///
+/// The dates of all the series are merged on the x-axis. If there are more dates than columns, neighbouring
+/// dates are grouped and their values averaged. The y-axis is scaled to the values shown (bar charts always
+/// start at zero), with the top, middle and bottom values labelled. Line charts mark each series with its own
+/// symbol and print a legend; bar charts put the bars of each series for a date side by side, numbered in the
+/// legend. Nothing is drawn if there are no points.
+pub fn chart(kind: ChartKind, series: &[Series], height: usize, width: usize) -> String {
+    let dates: Vec<&String> = series.iter()
+                                    .flat_map(|s| s.points.iter().map(|(d, _)| d))
+                                    .collect::<BTreeSet<_>>()
+                                    .into_iter()
+                                    .collect();
+    if dates.is_empty() || height == 0 {
+        return String::new();
+    }
+
+    //Bars take one column per series, so fewer dates fit in the same width
+    let per_date = if kind == ChartKind::Bar { series.len() } else { 1 };
+    let buckets = (width / per_date).clamp(1, dates.len());
+    let bucket_of = |date: &String| dates.iter().position(|d| *d == date).unwrap() * buckets / dates.len();
+
+    //Average the values of each series that land in the same bucket (None where a series has no data)
+    let columns: Vec<Vec<Option<f64>>> = series.iter().map(|s| {
+        let mut sums = vec![(0.0, 0); buckets];
+        for (date, value) in s.points.iter() {
+            let b = bucket_of(date);
+            sums[b].0 += value;
+            sums[b].1 += 1;
+        }
+        sums.iter().map(|(sum, n)| if *n > 0 { Some(sum / *n as f64) } else { None }).collect()
+    }).collect();
+
+    let (mut min, max) = bounds(columns.iter().flatten().flatten().copied());
+    if kind == ChartKind::Bar {
+        min = min.min(0.0);
+    }
+    let level = |v: f64| if max > min { ((v - min) / (max - min) * (height - 1) as f64).round() as usize } else { 0 };
+
+    //Fill a grid of characters, with row 0 at the top
+    let mut grid = vec![vec![' '; buckets * per_date]; height];
+    for (i, column) in columns.iter().enumerate() {
+        for (b, value) in column.iter().enumerate() {
+            let value = match value { Some(v) => *v, None => continue };
+            match kind {
+                ChartKind::Line => grid[height - 1 - level(value)][b] = MARKERS[i % MARKERS.len()],
+                ChartKind::Bar => {
+                    for row in grid.iter_mut().skip(height - 1 - level(value)) {
+                        row[b * per_date + i] = '█';
+                    }
+                }
+            }
+        }
+    }
+
+    //Label the top, middle and bottom rows of the y-axis
+    let labels = [axis_label(max), axis_label((max + min) / 2.0), axis_label(min)];
+    let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap();
+    let mut out = String::new();
+    for (r, row) in grid.iter().enumerate() {
+        let label = if r == 0 { &labels[0] } else if r == height / 2 { &labels[1] } else if r == height - 1 { &labels[2] } else { "" };
+        out.push_str(&format!("{:>w$} │{}\n", label, row.iter().collect::<String>(), w = label_width));
+    }
+    out.push_str(&format!("{:>w$} └{}\n", "", "─".repeat(buckets * per_date), w = label_width));
+
+    //Label the first and last dates of the x-axis
+    let first = dates[0].as_str();
+    let last = dates[dates.len() - 1].as_str();
+    let gap = (buckets * per_date).saturating_sub(first.len() + last.len());
+    if dates.len() > 1 && gap > 0 {
+        out.push_str(&format!("{:>w$}  {}{}{}\n", "", first, " ".repeat(gap), last, w = label_width));
+    }
+    else {
+        out.push_str(&format!("{:>w$}  {}\n", "", first, w = label_width));
+    }
+
+    //Print a legend of the series
+    for (i, s) in series.iter().enumerate() {
+        let marker = if kind == ChartKind::Line { MARKERS[i % MARKERS.len()] } else { '█' };
+        let position = if kind == ChartKind::Bar && series.len() > 1 { format!(" #{}", i + 1) } else { String::new() };
+        out.push_str(&format!("  {} {}{}\n", marker, s.label, position));
+    }
+    out
+}
+
+/// Returns the smallest and largest of the values, or (0, 0) if there are none.
+fn bounds(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    values.fold(None, |acc: Option<(f64, f64)>, v| match acc {
+        Some((min, max)) => Some((min.min(v), max.max(v))),
+        None => Some((v, v)),
+    }).unwrap_or((0.0, 0.0))
+}
+
+/// Formats a y-axis value, without decimals for whole numbers.
+fn axis_label(v: f64) -> String {
+    if v.fract() == 0.0 { format!("{}", v) } else { format!("{:.2}", v) }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::chart::{self, ChartKind, Series};
+
+    #[test]
+    fn test_chart() {
+        assert_eq!(chart::sparkline(&[0.0, 7.0, 14.0]), "▁▅█");
+        assert_eq!(chart::sparkline(&[3.0, 3.0]), "▁▁");
+
+        let series = vec![Series {
+            label: String::from("Ontario"),
+            points: vec![(String::from("2020-03-01"), 0.0), (String::from("2020-03-02"), 5.0), (String::from("2020-03-03"), 10.0)],
+        }];
+        let out = chart::chart(ChartKind::Bar, &series, 3, 80);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "10 │  █");
+        assert_eq!(lines[1], " 5 │ ██");
+        assert_eq!(lines[2], " 0 │███");
+
+        //More dates than columns are averaged into buckets
+        let out = chart::chart(ChartKind::Line, &series, 3, 2);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "  10 │ ●");
+        assert_eq!(lines[2], "2.50 │● ");
+    }
+}
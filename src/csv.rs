@@ -0,0 +1,200 @@
+//Final Project - Thomas Ivanov
+
+/// The byte order mark some programs (such as Excel) need at the start of a file to read it as UTF-8.
+pub const BOM: char = '\u{feff}';
+
+/// Delimiters recognized when reading a file.
+pub const DELIMITERS: [char; 3] = [',', ';', '\t'];
+
+/// Which fields are put in double quotes when writing. Fields that contain the delimiter, a quote or a line
+/// break are always quoted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Quote {
+    /// Only the fields that need it.
+    Minimal,
+    /// Every field.
+    All,
+    /// Every field that isn't a number, so spreadsheets keep names and dates as text.
+    Text,
+}
+
+/// How the working file is written: delimiter, decimal separator, line endings, quoting and byte order mark.
+///
+/// The default is a plain CSV file. `excel` writes what Excel expects on French-locale machines, where the
+/// comma is the decimal separator. The format is read back automatically: see `detect_delimiter`.
+///
+/// # Examples
+///
+/// ```
+/// let format = CsvFormat {delimiter: '\t', ..CsvFormat::default()};
+/// let line = format.write_line(&cells, &numeric);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CsvFormat {
+    pub delimiter: char,
+    pub decimal: char,
+    pub crlf: bool,
+    pub quote: Quote,
+    pub bom: bool,
+}
+
+impl Default for CsvFormat {
+    fn default() -> CsvFormat {
+        CsvFormat {
+            delimiter: ',',
+            decimal: '.',
+            crlf: false,
+            quote: Quote::Minimal,
+            bom: false,
+        }
+    }
+}
+
+impl CsvFormat {
+    /// Semicolons, decimal commas, Windows line endings and a byte order mark, for Excel in French.
+    pub fn excel() -> CsvFormat {
+        CsvFormat {delimiter: ';', decimal: ',', crlf: true, quote: Quote::Minimal, bom: true}
+    }
+
+    /// Sets one option by its settings name (`delimiter`, `decimal`, `line_endings`, `quote` or `bom`),
+    /// returning false if the value isn't valid.
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        let value = value.trim().to_lowercase();
+        match (key, value.as_str()) {
+            ("delimiter", "," | "comma") => self.delimiter = ',',
+            ("delimiter", ";" | "semicolon") => self.delimiter = ';',
+            ("delimiter", "tab" | "\\t") => self.delimiter = '\t',
+            ("decimal", "." | "point") => self.decimal = '.',
+            ("decimal", "," | "comma") => self.decimal = ',',
+            ("line_endings", "lf") => self.crlf = false,
+            ("line_endings", "crlf") => self.crlf = true,
+            ("quote", "minimal") => self.quote = Quote::Minimal,
+            ("quote", "all") => self.quote = Quote::All,
+            ("quote", "text") => self.quote = Quote::Text,
+            ("bom", "yes" | "true" | "on") => self.bom = true,
+            ("bom", "no" | "false" | "off") => self.bom = false,
+            _ => return false,
+        }
+        true
+    }
+
+    /// The line ending written after each line.
+    pub fn line_ending(&self) -> &'static str {
+        if self.crlf { "\r\n" } else { "\n" }
+    }
+
+    /// Formats one line of fields, without its line ending. `numeric` tells which fields are numbers (missing
+    /// entries count as text), for the decimal separator and `Quote::Text`.
+    pub fn write_line(&self, fields: &[String], numeric: &[bool]) -> String {
+        let fields: Vec<String> = fields.iter().enumerate().map(|(i, field)| {
+            let number = numeric.get(i).copied().unwrap_or(false);
+            let field = if number && self.decimal != '.' { field.replace('.', &self.decimal.to_string()) } else { field.clone() };
+            let needed = field.contains([self.delimiter, '"', '\n', '\r']);
+            let quoted = needed || match self.quote {
+                Quote::Minimal => false,
+                Quote::All => true,
+                Quote::Text => !number,
+            };
+            if quoted { format!("\"{}\"", field.replace('"', "\"\"")) } else { field }
+        }).collect();
+        fields.join(&self.delimiter.to_string())
+    }
+}
+
+/// Removes the byte order mark from the start of a file's contents if there is one.
+pub fn strip_bom(contents: &str) -> &str {
+    contents.strip_prefix(BOM).unwrap_or(contents)
+}
+
+/// Works out the delimiter of a file from its header line: whichever of `DELIMITERS` appears most often
+/// outside double quotes (a comma if none does).
+pub fn detect_delimiter(header: &str) -> char {
+    let mut quoted = false;
+    let mut counts = [0; DELIMITERS.len()];
+    for c in header.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        else if let Some(d) = DELIMITERS.iter().position(|d| *d == c).filter(|_| !quoted) {
+            counts[d] += 1;
+        }
+    }
+    //The first of the most frequent delimiters wins a tie, so a file without any is read with commas
+    let best = (0..DELIMITERS.len()).rev().max_by_key(|d| counts[*d]).unwrap_or(0);
+    DELIMITERS[best]
+}
+
+/// Splits a line into its fields at the delimiter, keeping delimiters inside double quotes and dropping the
+/// quotes (a quote is written twice inside a quoted field).
+pub fn split_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod test {
+    use crate::csv::{self, CsvFormat, Quote};
+
+    fn fields() -> (Vec<String>, [bool; 4]) {
+        (["24", "Québec", "Say \"hi\"; bye", "12.25"].iter().map(|f| f.to_string()).collect(), [true, false, false, true])
+    }
+
+    #[test]
+    fn test_write_line() {
+        let (fields, numeric) = fields();
+        assert_eq!(CsvFormat::default().write_line(&fields, &numeric), "24,Québec,\"Say \"\"hi\"\"; bye\",12.25");
+        assert_eq!(CsvFormat::excel().write_line(&fields, &numeric), "24;Québec;\"Say \"\"hi\"\"; bye\";12,25");
+    }
+
+    #[test]
+    fn test_write_line_quotes() {
+        let (fields, numeric) = fields();
+        let text = CsvFormat {delimiter: '\t', quote: Quote::Text, ..CsvFormat::default()};
+        assert_eq!(text.write_line(&fields, &numeric), "24\t\"Québec\"\t\"Say \"\"hi\"\"; bye\"\t12.25");
+        assert_eq!(CsvFormat {quote: Quote::All, ..CsvFormat::default()}.write_line(&fields[..1], &numeric), "\"24\"");
+
+        //A decimal comma is quoted when the comma is also the delimiter
+        assert_eq!(CsvFormat {decimal: ',', ..CsvFormat::default()}.write_line(&fields[3..], &numeric[3..]), "\"12,25\"");
+    }
+
+    #[test]
+    fn test_set() {
+        let mut format = CsvFormat::default();
+        assert!(format.set("delimiter", "TAB") && format.set("bom", "yes") && format.set("line_endings", "crlf"));
+        assert_eq!((format.delimiter, format.bom, format.line_ending()), ('\t', true, "\r\n"));
+        assert!(!format.set("delimiter", "|") && !format.set("quote", "never"));
+        assert_eq!(format, CsvFormat {delimiter: '\t', crlf: true, bom: true, ..CsvFormat::default()});
+    }
+
+    #[test]
+    fn test_detect_delimiter() {
+        assert_eq!(csv::strip_bom("\u{feff}pruid;date"), "pruid;date");
+        assert_eq!(csv::detect_delimiter("pruid;prname;\"a,b,c\";date"), ';');
+        assert_eq!(csv::detect_delimiter("pruid\tprname\tdate"), '\t');
+        assert_eq!(csv::detect_delimiter("pruid"), ',');
+    }
+
+    #[test]
+    fn test_split_line() {
+        let (fields, numeric) = fields();
+        assert_eq!(csv::split_line("24;\"Say \"\"hi\"\"; bye\";12,25", ';'), fields[..1].iter().cloned()
+                    .chain([String::from("Say \"hi\"; bye"), String::from("12,25")]).collect::<Vec<String>>());
+        let text = CsvFormat {delimiter: '\t', quote: Quote::Text, ..CsvFormat::default()};
+        assert_eq!(csv::split_line(&text.write_line(&fields, &numeric), '\t'), fields);
+    }
+}
@@ -1,9 +1,9 @@
 //Final Project - Thomas Ivanov
 
-use std::convert::TryInto;
-
 use crate::logic::Config;
 use crate::logic::Lang;
+use crate::layout::{Layout, terminal_width};
+use crate::locale;
 
 /// A data row object (struct) used to manipulate stored data from file.
 /// 
@@ -35,67 +35,79 @@ impl DataRow {
     /// A function for creating new `DataRow` structs from a vector.
     pub fn new(line: Vec<&str>) -> DataRow {
         DataRow {
-            pruid: if line[0] == "" {0} else {line[0].parse().unwrap()},
+            pruid: if line[0].is_empty() {0} else {line[0].parse().unwrap()},
             prname: String::from(line[1]),
             prname_fr: String::from(line[2]),
             date: String::from(line[3]),
-            numconf: if line[4] == "" {0} else {line[4].parse().unwrap()},
-            numprob: if line[5] == "" {0} else {line[5].parse().unwrap()},
-            numdeaths: if line[6] == "" {0} else {line[6].parse().unwrap()},
-            numtotal: if line[7] == "" {0} else {line[7].parse().unwrap()},
-            numtoday: if line[8] == "" {0} else {line[8].parse().unwrap()},
-            ratetotal: if line[9] == "" {0.00} else {line[9].parse().unwrap()},
+            numconf: if line[4].is_empty() {0} else {line[4].parse().unwrap()},
+            numprob: if line[5].is_empty() {0} else {line[5].parse().unwrap()},
+            numdeaths: if line[6].is_empty() {0} else {line[6].parse().unwrap()},
+            numtotal: if line[7].is_empty() {0} else {line[7].parse().unwrap()},
+            numtoday: if line[8].is_empty() {0} else {line[8].parse().unwrap()},
+            ratetotal: if line[9].is_empty() {0.00} else {line[9].parse().unwrap()},
         }
     }
 
     /// Produces a vector of strings representing the data row
     pub fn public_vec(&self) -> Vec<String> {
-        let mut v: Vec<String> = Vec::new();
-        v.push(self.pruid.clone().to_string());
-        v.push(self.prname.clone());
-        v.push(self.prname_fr.clone());
-        v.push(self.date.clone());
-        v.push(self.numconf.clone().to_string());
-        v.push(self.numprob.clone().to_string());
-        v.push(self.numdeaths.clone().to_string());
-        v.push(self.numtotal.clone().to_string());
-        v.push(self.numtoday.clone().to_string());
-        v.push(self.ratetotal.clone().to_string());
-        v
+        vec![
+            self.pruid.to_string(),
+            self.prname.clone(),
+            self.prname_fr.clone(),
+            self.date.clone(),
+            self.numconf.to_string(),
+            self.numprob.to_string(),
+            self.numdeaths.to_string(),
+            self.numtotal.to_string(),
+            self.numtoday.to_string(),
+            self.ratetotal.to_string(),
+        ]
     }
 
-    /// A function for outputting the formatted current-language version of the data.
-    pub fn output_lang(&self, config: &Config) -> String {
-        let mut line = String::new();
+    /// Returns the value of a numeric column by its label (e.g. "numtoday"), or `None` for other columns.
+    pub fn numeric(&self, column: &str) -> Option<f64> {
+        match column {
+            "pruid" => Some(self.pruid as f64),
+            "numconf" => Some(self.numconf as f64),
+            "numprob" => Some(self.numprob as f64),
+            "numdeaths" => Some(self.numdeaths as f64),
+            "numtotal" => Some(self.numtotal as f64),
+            "numtoday" => Some(self.numtoday as f64),
+            "ratetotal" => Some(self.ratetotal),
+            _ => None,
+        }
+    }
 
-        line.push_str(&col_spacing(self.pruid.to_string(), 8));
-        line.push_str(&col_spacing(match config.language {Lang::EN => self.prname.clone(), Lang::FR => self.prname_fr.clone()}, 30));
-        line.push_str(&col_spacing(self.date.clone(), 15));
-        line.push_str(&col_spacing(self.numconf.to_string(), 10));
-        line.push_str(&col_spacing(self.numprob.to_string(), 10));
-        line.push_str(&col_spacing(self.numdeaths.to_string(), 10));
-        line.push_str(&col_spacing(self.numtotal.to_string(), 10));
-        line.push_str(&col_spacing(self.numtoday.to_string(), 10));
-        line.push_str(&col_spacing(self.ratetotal.to_string(), 10));
+    /// Produces the cells of the data row shown in the current language (one province name column).
+    /// 
+    /// Counts, rates and the date are formatted for the language, e.g. "1,234" and "Mar 1, 2020" or
+    /// "1 234" and "1er mars 2020". Use `public_vec` for the stored form of the values.
+    pub fn cells_lang(&self, config: &Config) -> Vec<String> {
+        let lang = &config.language;
+        vec![
+            self.pruid.to_string(),
+            match lang {Lang::EN => self.prname.clone(), Lang::FR => self.prname_fr.clone()},
+            locale::format_date(&self.date, lang),
+            locale::format_int(self.numconf, lang),
+            locale::format_int(self.numprob, lang),
+            locale::format_int(self.numdeaths, lang),
+            locale::format_int(self.numtotal, lang),
+            locale::format_int(self.numtoday, lang),
+            locale::format_float(self.ratetotal, 2, lang),
+        ]
+    }
 
-        line
+    /// A function for outputting the formatted current-language version of the data.
+    /// 
+    /// The columns are only as wide as this row's values; use `layout::render_table` to line up several rows.
+    pub fn output_lang(&self, config: &Config) -> String {
+        let cells = self.cells_lang(config);
+        Layout::new(&[], std::slice::from_ref(&cells), terminal_width()).render(&cells)
     }
     /// A function for outputting data including both language-dependent columns
     pub fn output_all(&self) -> String {
-        let mut line = String::new();
-
-        line.push_str(&col_spacing(self.pruid.to_string(), 8));
-        line.push_str(&col_spacing(self.prname.clone(), 30));
-        line.push_str(&col_spacing(self.prname_fr.clone(), 30));
-        line.push_str(&col_spacing(self.date.clone(), 15));
-        line.push_str(&col_spacing(self.numconf.to_string(), 10));
-        line.push_str(&col_spacing(self.numprob.to_string(), 10));
-        line.push_str(&col_spacing(self.numdeaths.to_string(), 10));
-        line.push_str(&col_spacing(self.numtotal.to_string(), 10));
-        line.push_str(&col_spacing(self.numtoday.to_string(), 10));
-        line.push_str(&col_spacing(self.ratetotal.to_string(), 10));
-
-        line
+        let cells = self.public_vec();
+        Layout::new(&[], std::slice::from_ref(&cells), terminal_width()).render(&cells)
     }
 }
 
@@ -113,31 +125,6 @@ impl PartialEq for DataRow {
         self.numtoday == other.numtoday &&
         self.ratetotal == other.ratetotal
     }
-
-    fn ne(&self, other: &Self) -> bool {
-        self.pruid != other.pruid ||
-        self.prname != other.prname ||
-        self.prname_fr != other.prname_fr ||
-        self.date != other.date ||
-        self.numconf != other.numconf ||
-        self.numprob != other.numprob ||
-        self.numdeaths != other.numdeaths ||
-        self.numtotal != other.numtotal ||
-        self.numtoday != other.numtoday ||
-        self.ratetotal != other.ratetotal
-    }
-}
-
-/// Returns String with a dynamic number of spaces according to an input integer.
-fn col_spacing(mut s: String, x: usize) -> String {
-    let x: isize = (x - s.len()).try_into().unwrap();
-    let mut i = 0;
-    while i < (x-2) {
-        s.push(' ');
-        i += 1;
-    }
-    s.push('\t');
-    s
 }
 
 #[derive(Clone)]
@@ -154,38 +141,24 @@ impl Header {
         }
     }
     
-    /// A function for outputting the formatted English-language version of the data.
-    pub fn output_lang(&self, config: &Config) -> String {
-        let mut line = String::new();
-
-        line.push_str(&col_spacing(self.labels[0].clone(), 8));
-        line.push_str(&col_spacing(match config.language {Lang::EN => self.labels[1].clone(), Lang::FR => self.labels[2].clone()}, 30));
-        line.push_str(&col_spacing(self.labels[3].clone(), 15));
-        line.push_str(&col_spacing(self.labels[4].clone(), 10));
-        line.push_str(&col_spacing(self.labels[5].clone(), 10));
-        line.push_str(&col_spacing(self.labels[6].clone(), 10));
-        line.push_str(&col_spacing(self.labels[7].clone(), 10));
-        line.push_str(&col_spacing(self.labels[8].clone(), 10));
-        line.push_str(&col_spacing(self.labels[9].clone(), 10));
+    /// Produces the labels shown in the current language (one province name column).
+    pub fn labels_lang(&self, config: &Config) -> Vec<String> {
+        let mut labels = self.labels.clone();
+        match config.language {
+            Lang::EN => labels.remove(2),
+            Lang::FR => labels.remove(1),
+        };
+        labels
+    }
 
-        line
+    /// A function for outputting the formatted current-language version of the labels.
+    pub fn output_lang(&self, config: &Config) -> String {
+        let labels = self.labels_lang(config);
+        Layout::new(&labels, &[], terminal_width()).render(&labels)
     }
 
-    /// A function for outputting the formatted French-language version of the data.
+    /// A function for outputting the formatted labels of every column, including both languages.
     pub fn output_all(&self) -> String {
-        let mut line = String::new();
-
-        line.push_str(&col_spacing(self.labels[0].clone(), 8));
-        line.push_str(&col_spacing(self.labels[1].clone(), 30));
-        line.push_str(&col_spacing(self.labels[2].clone(), 30));
-        line.push_str(&col_spacing(self.labels[3].clone(), 15));
-        line.push_str(&col_spacing(self.labels[4].clone(), 10));
-        line.push_str(&col_spacing(self.labels[5].clone(), 10));
-        line.push_str(&col_spacing(self.labels[6].clone(), 10));
-        line.push_str(&col_spacing(self.labels[7].clone(), 10));
-        line.push_str(&col_spacing(self.labels[8].clone(), 10));
-        line.push_str(&col_spacing(self.labels[9].clone(), 10));
-
-        line
+        Layout::new(&self.labels, &[], terminal_width()).render(&self.labels)
     }
 }
\ No newline at end of file
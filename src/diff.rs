@@ -0,0 +1,124 @@
+//Final Project - Thomas Ivanov
+
+use std::collections::BTreeMap;
+
+use crate::datastore::DataRow;
+use crate::logic::Config;
+use crate::merge::{self, Key};
+use crate::messages::Msg;
+
+/// A difference between two datasets, for one `pruid` and `date`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added(DataRow),
+    Removed(DataRow),
+    Modified(DataRow, DataRow),
+}
+
+impl Change {
+    /// Returns the key of the row that changed.
+    pub fn key(&self) -> Key {
+        match self {
+            Change::Added(row) | Change::Removed(row) | Change::Modified(_, row) => merge::key(row),
+        }
+    }
+}
+
+/// Compares two datasets, matching rows by `pruid` and `date`, and returns the added, removed and modified
+/// rows in key order. When a key appears more than once in a dataset, only its first row is used.
+///
+/// # Examples
+///
+/// ```
+/// for change in diff(&saved, &download) {
+///     println!("{:?}", change.key());
+/// }
+/// ```
+pub fn diff(old: &[DataRow], new: &[DataRow]) -> Vec<Change> {
+    let mut old_rows: BTreeMap<Key, &DataRow> = BTreeMap::new();
+    for row in old.iter() {
+        old_rows.entry(merge::key(row)).or_insert(row);
+    }
+    let mut new_rows: BTreeMap<Key, &DataRow> = BTreeMap::new();
+    for row in new.iter() {
+        new_rows.entry(merge::key(row)).or_insert(row);
+    }
+
+    let mut changes: Vec<Change> = Vec::new();
+    for (key, row) in old_rows.iter() {
+        match new_rows.get(key) {
+            None => changes.push(Change::Removed((*row).clone())),
+            Some(new_row) if new_row != row => changes.push(Change::Modified((*row).clone(), (*new_row).clone())),
+            Some(_) => {}
+        }
+    }
+    for (key, row) in new_rows.iter() {
+        if !old_rows.contains_key(key) {
+            changes.push(Change::Added((*row).clone()));
+        }
+    }
+    changes.sort_by_key(|c| c.key());
+    changes
+}
+
+/// Counts the added, removed and modified rows among changes.
+pub fn counts(changes: &[Change]) -> (usize, usize, usize) {
+    let count = |f: fn(&Change) -> bool| changes.iter().filter(|c| f(c)).count();
+    (count(|c| matches!(c, Change::Added(_))), count(|c| matches!(c, Change::Removed(_))), count(|c| matches!(c, Change::Modified(..))))
+}
+
+/// Labels of the diff table in the current language: change, pruid, date, column, old value, new value.
+pub fn table_labels(config: &Config) -> Vec<String> {
+    vec![config.text(Msg::DiffChange).to_string(), String::from("pruid"), String::from("date"),
+         config.text(Msg::DiffColumn).to_string(), config.text(Msg::DiffOld).to_string(), config.text(Msg::DiffNew).to_string()]
+}
+
+/// Lays out changes as rows of the diff table: one row per changed field of a modified row (old → new value),
+/// and one row for each added or removed row. `labels` are the labels of the stored columns.
+pub fn table(changes: &[Change], labels: &[String], config: &Config) -> Vec<Vec<String>> {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for change in changes.iter() {
+        let (pruid, date) = change.key();
+        let (pruid, date) = (pruid.to_string(), date);
+        match change {
+            Change::Added(_) => rows.push(vec![config.text(Msg::DiffAdded).to_string(), pruid, date, String::new(), String::from("-"), String::from("-")]),
+            Change::Removed(_) => rows.push(vec![config.text(Msg::DiffRemoved).to_string(), pruid, date, String::new(), String::from("-"), String::from("-")]),
+            Change::Modified(old, new) => {
+                for field in merge::changed_fields(labels, old, new) {
+                    rows.push([vec![config.text(Msg::DiffModified).to_string(), pruid.clone(), date.clone()], field].concat());
+                }
+            }
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod test {
+    use crate::datastore::DataRow;
+    use crate::diff::{self, Change};
+    use crate::logic::{Config, Lang};
+
+    #[test]
+    fn test_diff() {
+        let row = |date: &str, total: &str| DataRow::new(vec!["35", "Ontario", "Ontario", date, total, "0", "0", total, "0", "0.0"]);
+        let old = vec![row("2020-03-02", "12"), row("2020-03-01", "10"), row("2020-03-03", "20")];
+        let new = vec![row("2020-03-01", "10"), row("2020-03-02", "15"), row("2020-03-04", "25")];
+
+        let changes = diff::diff(&old, &new);
+        assert_eq!(changes, vec![
+            Change::Modified(old[0].clone(), new[1].clone()),
+            Change::Removed(old[2].clone()),
+            Change::Added(new[2].clone()),
+        ]);
+
+        let config = Config {language: Lang::FR, ..Config::default()};
+        let labels: Vec<String> = ["pruid", "prname", "prnameFR", "date", "numconf", "numprob", "numdeaths", "numtotal", "numtoday", "ratetotal"]
+                                    .iter().map(|l| l.to_string()).collect();
+        let table = diff::table(&changes, &labels, &config);
+        assert_eq!(table.len(), 4);
+        assert_eq!(table[0], vec!["modifiée", "35", "2020-03-02", "numconf", "12", "15"]);
+        assert_eq!(table[3][..3], ["ajoutée", "35", "2020-03-04"]);
+        assert_eq!(diff::table_labels(&config)[0], "changement");
+    }
+}
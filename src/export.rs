@@ -0,0 +1,196 @@
+//Final Project - Thomas Ivanov
+
+use std::path::Path;
+
+use crate::layout::{self, Align, Layout};
+
+/// A document format a table can be exported to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// A Markdown pipe table.
+    Markdown,
+    /// A standalone HTML page with basic styling.
+    Html,
+    /// A LaTeX `tabular` environment, to `\input` into a report.
+    Latex,
+}
+
+impl Format {
+    /// Chooses a format from a file name's extension (`.md`, `.html`/`.htm` or `.tex`), ignoring case.
+    pub fn from_filename(filename: &str) -> Option<Format> {
+        let extension = Path::new(filename).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" => Some(Format::Markdown),
+            "html" | "htm" => Some(Format::Html),
+            "tex" => Some(Format::Latex),
+            _ => None,
+        }
+    }
+}
+
+/// Writes a header and rows of cells as a document in a format.
+///
+/// Columns are aligned like in the terminal (see `layout::Layout`): numbers right and text left. The title
+/// is only used by formats that are whole documents (HTML).
+///
+/// # Examples
+///
+/// ```
+/// let html = render(Format::Html, config.text(Msg::Title), &labels, &cells);
+/// fs::write("report.html", html)?;
+/// ```
+pub fn render(format: Format, title: &str, header: &[String], rows: &[Vec<String>]) -> String {
+    let aligns = Layout::new(header, rows, usize::MAX).aligns;
+    match format {
+        Format::Markdown => markdown(header, rows, &aligns),
+        Format::Html => html(title, header, rows, &aligns),
+        Format::Latex => latex(header, rows, &aligns),
+    }
+}
+
+fn markdown(header: &[String], rows: &[Vec<String>], aligns: &[Align]) -> String {
+    let escape = |cell: &String| cell.replace('|', "\\|");
+    let header: Vec<String> = header.iter().map(escape).collect();
+    let rows: Vec<Vec<String>> = rows.iter().map(|r| r.iter().map(escape).collect()).collect();
+
+    //Cells are padded to their column's width so the table also reads well as plain text
+    let widths: Vec<usize> = (0..aligns.len()).map(|c| {
+        rows.iter().filter_map(|r| r.get(c)).chain(header.get(c)).map(|cell| layout::text_width(cell)).max().unwrap_or(0).max(3)
+    }).collect();
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = widths.iter().enumerate().map(|(c, width)| {
+            let cell = cells.get(c).map_or("", |s| s.as_str());
+            let padding = " ".repeat(width - layout::text_width(cell));
+            match aligns[c] {
+                Align::Left => format!("{}{}", cell, padding),
+                Align::Right => format!("{}{}", padding, cell),
+            }
+        }).collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+
+    let mut s = line(&header);
+    let rules: Vec<String> = widths.iter().zip(aligns.iter()).map(|(width, align)| match align {
+        Align::Left => "-".repeat(*width),
+        Align::Right => format!("{}:", "-".repeat(width - 1)),
+    }).collect();
+    s.push_str(&format!("| {} |\n", rules.join(" | ")));
+    for row in rows.iter() {
+        s.push_str(&line(row));
+    }
+    s
+}
+
+fn html(title: &str, header: &[String], rows: &[Vec<String>], aligns: &[Align]) -> String {
+    let escape = |cell: &str| cell.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+    let class = |c: usize| match aligns.get(c) {
+        Some(Align::Right) => " class=\"num\"",
+        _ => "",
+    };
+
+    let mut s = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    s.push_str(&format!("<title>{}</title>\n", escape(title)));
+    s.push_str("<style>\n");
+    s.push_str("body { font-family: sans-serif; }\n");
+    s.push_str("table { border-collapse: collapse; }\n");
+    s.push_str("th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n");
+    s.push_str("th { background: #eee; }\n");
+    s.push_str("tr:nth-child(even) td { background: #f8f8f8; }\n");
+    s.push_str(".num { text-align: right; font-variant-numeric: tabular-nums; }\n");
+    s.push_str("</style>\n</head>\n<body>\n");
+    s.push_str(&format!("<h1>{}</h1>\n<table>\n<thead>\n<tr>", escape(title)));
+    for (c, label) in header.iter().enumerate() {
+        s.push_str(&format!("<th{}>{}</th>", class(c), escape(label)));
+    }
+    s.push_str("</tr>\n</thead>\n<tbody>\n");
+    for row in rows.iter() {
+        s.push_str("<tr>");
+        for (c, cell) in row.iter().enumerate() {
+            s.push_str(&format!("<td{}>{}</td>", class(c), escape(cell)));
+        }
+        s.push_str("</tr>\n");
+    }
+    s.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+    s
+}
+
+fn latex(header: &[String], rows: &[Vec<String>], aligns: &[Align]) -> String {
+    let escape = |cell: &String| {
+        let mut out = String::new();
+        for c in cell.chars() {
+            match c {
+                '\\' => out.push_str("\\textbackslash{}"),
+                '&' | '%' | '$' | '#' | '_' | '{' | '}' => {out.push('\\'); out.push(c);}
+                '~' => out.push_str("\\textasciitilde{}"),
+                '^' => out.push_str("\\textasciicircum{}"),
+                //The spaces between thousands in French numbers
+                '\u{202f}' => out.push_str("\\,"),
+                '\u{a0}' => out.push('~'),
+                c => out.push(c),
+            }
+        }
+        out
+    };
+    let line = |cells: &[String]| format!("{} \\\\\n", cells.iter().map(escape).collect::<Vec<String>>().join(" & "));
+
+    let spec: String = aligns.iter().map(|a| match a {
+        Align::Left => 'l',
+        Align::Right => 'r',
+    }).collect();
+    let mut s = format!("\\begin{{tabular}}{{{}}}\n\\hline\n", spec);
+    s.push_str(&line(header));
+    s.push_str("\\hline\n");
+    for row in rows.iter() {
+        s.push_str(&line(row));
+    }
+    s.push_str("\\hline\n\\end{tabular}\n");
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use crate::export::{self, Format};
+
+    fn table() -> (Vec<String>, Vec<Vec<String>>) {
+        let header = ["prname", "date", "numconf"].iter().map(|s| s.to_string()).collect();
+        let rows = vec![vec![String::from("Québec"), String::from("2020-03-01"), String::from("1\u{202f}234")],
+                        vec![String::from("A|B & C"), String::from("2020-03-02"), String::from("7")]];
+        (header, rows)
+    }
+
+    #[test]
+    fn test_format_from_filename() {
+        assert_eq!(Format::from_filename("report.HTML"), Some(Format::Html));
+        assert_eq!(Format::from_filename("report.md"), Some(Format::Markdown));
+        assert_eq!(Format::from_filename("report.tex"), Some(Format::Latex));
+        assert_eq!(Format::from_filename("report.csv"), None);
+    }
+
+    #[test]
+    fn test_markdown() {
+        let (header, rows) = table();
+        let md = export::render(Format::Markdown, "Title", &header, &rows);
+        assert_eq!(md.lines().collect::<Vec<&str>>(), vec![
+            "| prname   | date       | numconf |",
+            "| -------- | ---------- | ------: |",
+            "| Québec   | 2020-03-01 |   1\u{202f}234 |",
+            "| A\\|B & C | 2020-03-02 |       7 |",
+        ]);
+    }
+
+    #[test]
+    fn test_html() {
+        let (header, rows) = table();
+        let html = export::render(Format::Html, "Covid <data>", &header, &rows);
+        assert!(html.contains("<title>Covid &lt;data&gt;</title>"));
+        assert!(html.contains("<td>A|B &amp; C</td><td>2020-03-02</td><td class=\"num\">7</td>"));
+    }
+
+    #[test]
+    fn test_latex() {
+        let (header, rows) = table();
+        let tex = export::render(Format::Latex, "Title", &header, &rows);
+        assert!(tex.starts_with("\\begin{tabular}{llr}\n"));
+        assert!(tex.contains("Québec & 2020-03-01 & 1\\,234 \\\\\nA|B \\& C & 2020-03-02 & 7 \\\\\n"));
+    }
+}
@@ -0,0 +1,183 @@
+//Final Project - Thomas Ivanov
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::datastore::DataRow;
+
+/// Secondary indexes over a vector of data rows, used to find rows without scanning the whole table.
+///
+/// An `Index` keeps a hash index on `pruid`, a hash index on province name (both languages, case-insensitive)
+/// and an ordered index on `date`. Each index maps a key to the positions of the matching rows in the
+/// data vector, so it has to be told about every insert, edit and delete made to that vector.
+///
+/// # Examples
+///
+/// ```
+/// let index = Index::new(&data);
+/// let ontario = index.pruid(35);
+/// let march = index.date_range("2020-03-01", "2020-03-31");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Index {
+    by_pruid: HashMap<isize, Vec<usize>>,
+    by_prname: HashMap<String, Vec<usize>>,
+    by_date: BTreeMap<String, Vec<usize>>,
+}
+
+impl Index {
+    /// A function for building a new `Index` over every row of a data vector.
+    pub fn new(data: &[DataRow]) -> Index {
+        let mut index = Index::default();
+        for (pos, row) in data.iter().enumerate() {
+            index.add(pos, row);
+        }
+        index
+    }
+
+    /// Records a row inserted at position `pos`, shifting the positions of the rows after it.
+    pub fn insert(&mut self, pos: usize, row: &DataRow) {
+        self.shift(pos, 1);
+        self.add(pos, row);
+    }
+
+    /// Records an edit of the row at position `pos` from `old` to `new`.
+    pub fn update(&mut self, pos: usize, old: &DataRow, new: &DataRow) {
+        self.drop_entry(pos, old);
+        self.add(pos, new);
+    }
+
+    /// Records the removal of the row at position `pos`, shifting the positions of the rows after it.
+    pub fn remove(&mut self, pos: usize, row: &DataRow) {
+        self.drop_entry(pos, row);
+        self.shift(pos + 1, -1);
+    }
+
+    /// Positions of every row with the given `pruid`, in table order.
+    pub fn pruid(&self, pruid: isize) -> Vec<usize> {
+        self.by_pruid.get(&pruid).cloned().unwrap_or_default()
+    }
+
+    /// Every `pruid` in the table, in ascending order.
+    pub fn pruids(&self) -> Vec<isize> {
+        let mut pruids: Vec<isize> = self.by_pruid.keys().copied().collect();
+        pruids.sort_unstable();
+        pruids
+    }
+
+    /// Positions of every row whose English or French province name matches `name`, in table order.
+    pub fn prname(&self, name: &str) -> Vec<usize> {
+        self.by_prname.get(&name.trim().to_lowercase()).cloned().unwrap_or_default()
+    }
+
+    /// Positions of every row dated between `from` and `to` (both inclusive), in date order.
+    pub fn date_range(&self, from: &str, to: &str) -> Vec<usize> {
+        if from > to {
+            return Vec::new();
+        }
+        self.by_date.range(from.to_string()..=to.to_string())
+                        .flat_map(|(_, positions)| positions.iter().copied())
+                        .collect()
+    }
+
+    /// Adds a row to each index without touching any other positions.
+    fn add(&mut self, pos: usize, row: &DataRow) {
+        insert_sorted(self.by_pruid.entry(row.pruid).or_default(), pos);
+        insert_sorted(self.by_date.entry(row.date.clone()).or_default(), pos);
+        for name in name_keys(row) {
+            insert_sorted(self.by_prname.entry(name).or_default(), pos);
+        }
+    }
+
+    /// Removes a row from each index without touching any other positions.
+    fn drop_entry(&mut self, pos: usize, row: &DataRow) {
+        if remove_sorted(self.by_pruid.get_mut(&row.pruid), pos) {
+            self.by_pruid.remove(&row.pruid);
+        }
+        if remove_sorted(self.by_date.get_mut(&row.date), pos) {
+            self.by_date.remove(&row.date);
+        }
+        for name in name_keys(row) {
+            if remove_sorted(self.by_prname.get_mut(&name), pos) {
+                self.by_prname.remove(&name);
+            }
+        }
+    }
+
+    /// Moves every stored position at or after `from` by `by` (1 for an insert, -1 for a delete).
+    fn shift(&mut self, from: usize, by: isize) {
+        let positions = self.by_pruid.values_mut()
+                            .chain(self.by_prname.values_mut())
+                            .chain(self.by_date.values_mut());
+        for list in positions {
+            for p in list.iter_mut().filter(|p| **p >= from) {
+                *p = (*p as isize + by) as usize;
+            }
+        }
+    }
+}
+
+/// The province name keys of a row (English and French, lowercased, without duplicates).
+fn name_keys(row: &DataRow) -> Vec<String> {
+    let mut keys = vec![row.prname.trim().to_lowercase()];
+    let fr = row.prname_fr.trim().to_lowercase();
+    if fr != keys[0] {
+        keys.push(fr);
+    }
+    keys
+}
+
+/// Inserts a position into a sorted list of positions.
+fn insert_sorted(list: &mut Vec<usize>, pos: usize) {
+    if let Err(i) = list.binary_search(&pos) {
+        list.insert(i, pos);
+    }
+}
+
+/// Removes a position from a sorted list of positions, returning true if the list is left empty.
+fn remove_sorted(list: Option<&mut Vec<usize>>, pos: usize) -> bool {
+    match list {
+        Some(list) => {
+            if let Ok(i) = list.binary_search(&pos) {
+                list.remove(i);
+            }
+            list.is_empty()
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::index::Index;
+    use crate::logic;
+    use crate::persistence;
+
+    #[test]
+    fn test_index_consistency() {
+        let config = logic::Config {language: logic::Lang::EN, filename: String::from("covid19-download.csv"), ..logic::Config::default()};
+        let mut data = persistence::load_data(&config).unwrap().1;
+        let mut index = Index::new(&data);
+
+        let ontario: Vec<usize> = (0..data.len()).filter(|i| data[*i].pruid == 35).collect();
+        assert_eq!(index.pruid(35), ontario);
+        assert_eq!(index.prname("ONTARIO"), ontario);
+
+        //Delete the first row, then check positions shifted down
+        let row = data.remove(0);
+        index.remove(0, &row);
+        assert_eq!(index, Index::new(&data));
+
+        //Edit a row's date, then check it moved in the date index
+        let old = data[3].clone();
+        data[3].date = String::from("2019-12-31");
+        index.update(3, &old, &data[3]);
+        assert_eq!(index.date_range("2019-01-01", "2019-12-31"), vec![3]);
+        assert_eq!(index, Index::new(&data));
+
+        //Insert the deleted row back at the front
+        data.insert(0, row.clone());
+        index.insert(0, &row);
+        assert_eq!(index, Index::new(&data));
+    }
+}
@@ -0,0 +1,162 @@
+//Final Project - Thomas Ivanov
+
+use std::fmt;
+
+/// The contents of an INI-style settings file: `[section]` headers followed by `key = value` lines.
+///
+/// Sections, keys and comments (lines starting with `;` or `#`) keep the order they were read in, so a file
+/// written back with `to_string` only changes where values were set. Section names and keys are lowercased,
+/// and blank lines are only kept between sections.
+///
+/// # Examples
+///
+/// ```
+/// let mut ini = Ini::parse("[views]\ncompact = pruid, date, numtoday\n")?;
+/// assert_eq!(ini.get("views", "compact"), Some("pruid, date, numtoday"));
+/// ini.set("display", "view", "compact");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Ini {
+    /// The lines of each section. Comments before the first section are kept in a section named `""`,
+    /// which is written without a header.
+    pub sections: Vec<(String, Vec<Line>)>,
+}
+
+/// A line of a section: a setting, or a comment kept so it can be written back.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Line {
+    Setting(String, String),
+    Comment(String),
+}
+
+impl Ini {
+    /// Reads INI text, returning the number of the first line that isn't a section, a setting, a comment or blank.
+    pub fn parse(text: &str) -> Result<Ini, usize> {
+        let mut ini = Ini::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim().trim_start_matches('\u{feff}');
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with(';') || line.starts_with('#') {
+                if ini.sections.is_empty() {
+                    ini.sections.push((String::new(), Vec::new()));
+                }
+                ini.sections.last_mut().unwrap().1.push(Line::Comment(line.to_string()));
+            }
+            else if line.starts_with('[') && line.ends_with(']') {
+                ini.sections.push((line[1..line.len() - 1].trim().to_lowercase(), Vec::new()));
+            }
+            else if let (Some((key, value)), Some((_, lines))) = (line.split_once('='), ini.sections.last_mut().filter(|(s, _)| !s.is_empty())) {
+                lines.push(Line::Setting(key.trim().to_lowercase(), value.trim().to_string()));
+            }
+            else {
+                return Err(i + 1);
+            }
+        }
+        Ok(ini)
+    }
+
+    /// Returns the value of a setting, if it is set.
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.section(section).filter(|(k, _)| *k == key).last().map(|(_, v)| v.as_str())
+    }
+
+    /// Returns every setting of a section, in file order (none if the section doesn't exist).
+    pub fn section(&self, section: &str) -> impl Iterator<Item = (&String, &String)> {
+        let lines = self.sections.iter().find(|(s, _)| s == section).map_or(&[][..], |(_, lines)| lines.as_slice());
+        lines.iter().filter_map(setting)
+    }
+
+    /// Returns every setting of the file as its section, key and value, in file order.
+    pub fn settings(&self) -> impl Iterator<Item = (&String, &String, &String)> {
+        self.sections.iter().flat_map(|(section, lines)| lines.iter().filter_map(setting).map(move |(k, v)| (section, k, v)))
+    }
+
+    /// Sets a value, replacing the setting if it exists and adding it (and its section) otherwise.
+    pub fn set(&mut self, section: &str, key: &str, value: &str) {
+        let lines = match self.sections.iter().position(|(s, _)| s == section) {
+            Some(i) => &mut self.sections[i].1,
+            None => {
+                self.sections.push((section.to_string(), Vec::new()));
+                &mut self.sections.last_mut().unwrap().1
+            }
+        };
+        match lines.iter_mut().find(|line| matches!(line, Line::Setting(k, _) if k == key)) {
+            Some(line) => *line = Line::Setting(key.to_string(), value.to_string()),
+            None => lines.push(Line::Setting(key.to_string(), value.to_string())),
+        }
+    }
+
+    /// Removes a setting, if it is set.
+    pub fn remove(&mut self, section: &str, key: &str) {
+        if let Some((_, lines)) = self.sections.iter_mut().find(|(s, _)| s == section) {
+            lines.retain(|line| !matches!(line, Line::Setting(k, _) if k == key));
+        }
+    }
+}
+
+/// The key and value of a line, if it is a setting.
+fn setting(line: &Line) -> Option<(&String, &String)> {
+    match line {
+        Line::Setting(key, value) => Some((key, value)),
+        Line::Comment(_) => None,
+    }
+}
+
+impl fmt::Display for Ini {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (section, lines)) in self.sections.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            if !section.is_empty() {
+                writeln!(f, "[{}]", section)?;
+            }
+            for line in lines.iter() {
+                match line {
+                    Line::Setting(key, value) => writeln!(f, "{} = {}", key, value)?,
+                    Line::Comment(comment) => writeln!(f, "{}", comment)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ini::Ini;
+
+    #[test]
+    fn test_ini_round_trip() {
+        let text = "; saved views\n[Views]\ncompact = pruid, date, numtoday\n\n[display]\nview=compact\n";
+        let mut ini = Ini::parse(text).unwrap();
+        assert_eq!(ini.get("views", "compact"), Some("pruid, date, numtoday"));
+        assert_eq!(ini.get("display", "view"), Some("compact"));
+        assert_eq!(ini.get("display", "missing"), None);
+
+        ini.set("display", "view", "all");
+        ini.set("general", "language", "fr");
+        ini.remove("views", "compact");
+        assert_eq!(ini.to_string(), "; saved views\n\n[views]\n\n[display]\nview = all\n\n[general]\nlanguage = fr\n");
+        assert_eq!(Ini::parse(&ini.to_string()).unwrap(), ini);
+
+        assert_eq!(Ini::parse("no section = here"), Err(1));
+        assert_eq!(Ini::parse("# a comment\nno section = here"), Err(2));
+        assert_eq!(Ini::parse("[a]\nnot a setting"), Err(2));
+    }
+
+    #[test]
+    fn test_ini_comments() {
+        let text = "[views]\n; columns for the daily report\ndaily = date, numtoday\n# old\nweekly = date, numtotal\n";
+        let mut ini = Ini::parse(text).unwrap();
+        assert_eq!(ini.section("views").map(|(k, _)| k.as_str()).collect::<Vec<&str>>(), vec!["daily", "weekly"]);
+
+        //Comments stay where they were when settings around them are changed or removed
+        ini.set("views", "daily", "date, numtoday, numdeaths");
+        ini.remove("views", "weekly");
+        ini.set("views", "monthly", "date");
+        assert_eq!(ini.to_string(), "[views]\n; columns for the daily report\ndaily = date, numtoday, numdeaths\n# old\nmonthly = date\n");
+    }
+}
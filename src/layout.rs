@@ -0,0 +1,205 @@
+//Final Project - Thomas Ivanov
+
+use std::env;
+use terminal_size::{terminal_size, Width};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Spaces printed between two columns.
+const GAP: usize = 2;
+
+/// Narrowest a column is shrunk to when the table doesn't fit the terminal.
+const MIN_WIDTH: usize = 4;
+
+/// Terminal width used when it can't be read from the environment or the terminal.
+const DEFAULT_WIDTH: usize = 120;
+
+/// Horizontal alignment of a column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// Column widths and alignments measured from a page of cells.
+///
+/// A `Layout` is computed from the header and rows that are about to be printed, so every column is
+/// exactly as wide as its widest cell. Columns of numbers are aligned right and text columns left. If
+/// the table is wider than `max_width`, columns are narrowed (long names first, then headers that are
+/// wider than their values, then anything else) and cells that no longer fit are cut short with an ellipsis.
+///
+/// # Examples
+///
+/// ```
+/// let layout = Layout::new(&header, &rows, terminal_width());
+/// println!("{}", layout.render(&header));
+/// for row in rows.iter() {
+///     println!("{}", layout.render(row));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub widths: Vec<usize>,
+    pub aligns: Vec<Align>,
+}
+
+impl Layout {
+    /// A function for measuring a new `Layout` from a header line and rows of cells.
+    pub fn new(header: &[String], rows: &[Vec<String>], max_width: usize) -> Layout {
+        let columns = rows.iter().map(|r| r.len()).chain(std::iter::once(header.len())).max().unwrap_or(0);
+        let mut widths = vec![0; columns];
+        let mut aligns = vec![Align::Right; columns];
+
+        for (c, width) in widths.iter_mut().enumerate() {
+            let cells = rows.iter().filter_map(|r| r.get(c)).chain(header.get(c));
+            *width = cells.map(|cell| text_width(cell)).max().unwrap_or(0);
+        }
+        for (c, align) in aligns.iter_mut().enumerate() {
+            //A column is numeric if every non-empty cell (other than a "-" placeholder) is a number
+            let numeric = rows.iter()
+                                .filter_map(|r| r.get(c))
+                                .filter(|cell| !cell.is_empty() && cell.as_str() != "-")
+                                .all(|cell| cell.replace([',', ' ', '\u{a0}', '\u{202f}'], "").parse::<f64>().is_ok());
+            if !numeric {
+                *align = Align::Left;
+            }
+        }
+
+        //Narrow the widest column one step at a time until the table fits: first text columns down to the width
+        //of their typical (median) cell, then any column down to its widest value (cutting only the header),
+        //and only then any column down to the minimum width
+        let medians: Vec<usize> = (0..columns).map(|c| match aligns[c] {
+            Align::Left => median_width(rows, c).max(MIN_WIDTH),
+            Align::Right => widths[c],
+        }).collect();
+        let values: Vec<usize> = (0..columns).map(|c| {
+            rows.iter().filter_map(|r| r.get(c)).map(|cell| text_width(cell)).max().unwrap_or(0).max(MIN_WIDTH)
+        }).collect();
+        let total = |widths: &Vec<usize>| widths.iter().sum::<usize>() + GAP * widths.len().saturating_sub(1);
+        while total(&widths) > max_width {
+            let widest = |floor: &dyn Fn(usize) -> usize| (0..columns).filter(|c| widths[*c] > floor(*c)).max_by_key(|c| widths[*c]);
+            match widest(&|c| medians[c]).or_else(|| widest(&|c| values[c])).or_else(|| widest(&|_| MIN_WIDTH)) {
+                Some(c) => widths[c] -= 1,
+                None => break,
+            }
+        }
+
+        Layout {
+            widths,
+            aligns,
+        }
+    }
+
+    /// Formats one line of cells in the measured columns.
+    pub fn render(&self, cells: &[String]) -> String {
+        let mut line = String::new();
+        for (c, width) in self.widths.iter().enumerate() {
+            let cell = truncate(cells.get(c).map_or("", |s| s.as_str()), *width);
+            let padding = " ".repeat(width - text_width(&cell));
+            if c > 0 {
+                line.push_str(&" ".repeat(GAP));
+            }
+            match self.aligns[c] {
+                Align::Left => {line.push_str(&cell); line.push_str(&padding);}
+                Align::Right => {line.push_str(&padding); line.push_str(&cell);}
+            }
+        }
+        String::from(line.trim_end())
+    }
+}
+
+/// Lays out a header and rows of cells for the terminal and returns the formatted lines, header first.
+pub fn render_table(header: &[String], rows: &[Vec<String>]) -> Vec<String> {
+    let layout = Layout::new(header, rows, terminal_width());
+    std::iter::once(header).chain(rows.iter().map(|r| r.as_slice()))
+                        .map(|cells| layout.render(cells))
+                        .collect()
+}
+
+/// Width of the terminal in characters, from the `COLUMNS` environment variable if it is set, else from the terminal itself.
+pub fn terminal_width() -> usize {
+    match env::var("COLUMNS").ok().and_then(|c| c.trim().parse().ok()) {
+        Some(width) => width,
+        None => terminal_size().map_or(DEFAULT_WIDTH, |(Width(w), _)| w as usize),
+    }
+}
+
+/// Median width of the cells in column `c` (0 if there are none).
+fn median_width(rows: &[Vec<String>], c: usize) -> usize {
+    let mut lengths: Vec<usize> = rows.iter().filter_map(|r| r.get(c)).map(|cell| text_width(cell)).collect();
+    lengths.sort_unstable();
+    lengths.get(lengths.len().saturating_sub(1) / 2).copied().unwrap_or(0)
+}
+
+/// Number of columns a piece of text takes up on screen.
+/// 
+/// This counts display width rather than bytes or chars, so an accented letter written with a combining
+/// mark (e.g. "Que\u{301}bec") counts once and East Asian wide characters count twice.
+pub fn text_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// Cuts text down to `width` columns, ending it with an ellipsis if anything was cut.
+/// Text is only cut between grapheme clusters, so a letter is never separated from its accents.
+pub fn truncate(s: &str, width: usize) -> String {
+    if text_width(s) <= width {
+        return String::from(s);
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for grapheme in s.graphemes(true) {
+        let w = text_width(grapheme);
+        if used + w > width - 1 {
+            break;
+        }
+        out.push_str(grapheme);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::layout::{self, Align, Layout};
+
+    fn cells(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_layout() {
+        let header = cells(&["pruid", "prname", "numtotal"]);
+        let rows = vec![cells(&["35", "Ontario", "1234567890123"]), cells(&["99", "Repatriated travellers", "-"])];
+
+        let layout = Layout::new(&header, &rows, 120);
+        assert_eq!(layout.widths, vec![5, 22, 13]);
+        assert_eq!(layout.aligns, vec![Align::Right, Align::Left, Align::Right]);
+        assert_eq!(layout.render(&rows[0]), "   35  Ontario                 1234567890123");
+
+        //Too narrow: the name column is narrowed and cut short with an ellipsis
+        let layout = Layout::new(&header, &rows, 34);
+        assert_eq!(layout.widths, vec![5, 12, 13]);
+        assert_eq!(layout.render(&rows[1]), "   99  Repatriated…              -");
+    }
+
+    #[test]
+    fn test_unicode_width() {
+        //Precomposed and combining-mark accents, and a wide character, all line up
+        assert_eq!(layout::text_width("Québec"), 6);
+        assert_eq!(layout::text_width("Que\u{301}bec"), 6);
+        assert_eq!(layout::text_width("東京"), 4);
+        assert_eq!(layout::truncate("Île-du-Prince-Édouard", 5), "Île-…");
+        assert_eq!(layout::truncate("I\u{302}le-du-Prince-E\u{301}douard", 5), "I\u{302}le-…");
+        assert_eq!(layout::truncate("東京都", 4), "東…");
+
+        let header = cells(&["prnameFR", "numtotal"]);
+        let rows = vec![cells(&["Que\u{301}bec", "17"]), cells(&["Ontario", "79"])];
+        let lines = layout::render_table(&header, &rows);
+        assert_eq!(lines[1], format!("Que\u{301}bec{}17", " ".repeat(10)));
+        assert_eq!(lines[2], format!("Ontario{}79", " ".repeat(9)));
+    }
+}
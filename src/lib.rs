@@ -0,0 +1,45 @@
+//Final Project - Thomas Ivanov
+
+//! The table engine behind the Table-DB command-line tool, for other Rust tools to reuse.
+//!
+//! Tables are loaded and saved with `persistence` (or `logic::load` and `logic::save`), held as a `Header`
+//! and a vector of `DataRow`s from `datastore`, and queried, sorted and edited with the functions in `logic`.
+//! Every function takes a `logic::Config` for the files and the language of its messages.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::collections::BTreeSet;
+//! use table_db::logic::{self, Config};
+//!
+//! let config = Config {filename: String::from("datastore.csv"), ..Config::default()};
+//! let (column_labels, mut data, mut stamp) = logic::load_stamped(&config)?;
+//! logic::sort(&mut data, "3", &config)?;
+//! logic::save(&column_labels, &data, &BTreeSet::new(), &config, &mut stamp)?;
+//! ```
+
+pub mod logic;
+pub mod arrow;
+pub mod batch;
+pub mod chart;
+pub mod csv;
+pub mod datastore;
+pub mod diff;
+pub mod export;
+pub mod index;
+pub mod ini;
+pub mod layout;
+pub mod locale;
+pub mod merge;
+pub mod messages;
+pub mod ops;
+pub mod pager;
+pub mod persistence;
+pub mod province;
+pub mod rates;
+pub mod schema;
+pub mod server;
+pub mod sql;
+pub mod timeseries;
+pub mod validate;
+pub mod views;
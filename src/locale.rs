@@ -0,0 +1,123 @@
+//Final Project - Thomas Ivanov
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::logic::Lang;
+
+/// Month names used for dates in each language.
+const MONTHS_EN: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+const MONTHS_FR: [&str; 12] = ["janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.", "déc."];
+
+/// Separator between groups of three digits: a comma in English, a no-break space in French (so a number is never split across lines).
+fn group_separator(lang: &Lang) -> char {
+    match lang {
+        Lang::EN => ',',
+        Lang::FR => '\u{a0}',
+    }
+}
+
+/// Separator between the whole and fractional parts of a number: a point in English, a comma in French.
+fn decimal_separator(lang: &Lang) -> char {
+    match lang {
+        Lang::EN => '.',
+        Lang::FR => ',',
+    }
+}
+
+/// Formats a whole number with digit grouping for a language.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(format_int(1234567, &Lang::EN), "1,234,567");
+/// assert_eq!(format_int(-1234, &Lang::FR), "-1\u{a0}234");
+/// ```
+pub fn format_int(n: isize, lang: &Lang) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut out = String::new();
+    if n < 0 {
+        out.push('-');
+    }
+    out.push_str(&group(&digits, lang));
+    out
+}
+
+/// Formats a number with digit grouping and exactly `decimals` digits after the decimal separator for a language.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(format_float(1234567.891, 2, &Lang::EN), "1,234,567.89");
+/// assert_eq!(format_float(1234567.891, 2, &Lang::FR), "1\u{a0}234\u{a0}567,89");
+/// ```
+pub fn format_float(v: f64, decimals: usize, lang: &Lang) -> String {
+    let fixed = format!("{:.*}", decimals, v.abs());
+    let (whole, fraction) = match fixed.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (fixed.as_str(), None),
+    };
+
+    let mut out = String::new();
+    //Values that round to zero are printed without a sign
+    if v < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        out.push('-');
+    }
+    out.push_str(&group(whole, lang));
+    if let Some(fraction) = fraction {
+        out.push(decimal_separator(lang));
+        out.push_str(fraction);
+    }
+    out
+}
+
+/// Formats a stored `YYYY-MM-DD` date for a language, e.g. "Mar 1, 2020" or "1 mars 2020".
+/// Dates that can't be parsed are returned as they are.
+pub fn format_date(date: &str, lang: &Lang) -> String {
+    match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(d) => {
+            let month = d.month0() as usize;
+            match lang {
+                Lang::EN => format!("{} {}, {}", MONTHS_EN[month], d.day(), d.year()),
+                Lang::FR => format!("{} {} {}", if d.day() == 1 { String::from("1er") } else { d.day().to_string() }, MONTHS_FR[month], d.year()),
+            }
+        }
+        Err(_) => String::from(date),
+    }
+}
+
+/// Inserts the group separator between every three digits of a string of digits, counting from the right.
+fn group(digits: &str, lang: &Lang) -> String {
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(group_separator(lang));
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use crate::locale;
+    use crate::logic::Lang;
+
+    #[test]
+    fn test_locale_formats() {
+        assert_eq!(locale::format_int(0, &Lang::EN), "0");
+        assert_eq!(locale::format_int(999, &Lang::EN), "999");
+        assert_eq!(locale::format_int(1234567, &Lang::EN), "1,234,567");
+        assert_eq!(locale::format_int(-1234, &Lang::FR), "-1\u{a0}234");
+
+        assert_eq!(locale::format_float(1234567.891, 2, &Lang::EN), "1,234,567.89");
+        assert_eq!(locale::format_float(1234567.891, 2, &Lang::FR), "1\u{a0}234\u{a0}567,89");
+        assert_eq!(locale::format_float(0.02, 2, &Lang::FR), "0,02");
+        assert_eq!(locale::format_float(-0.001, 2, &Lang::EN), "0.00");
+        assert_eq!(locale::format_float(-12.5, 0, &Lang::EN), "-12");
+
+        assert_eq!(locale::format_date("2020-03-01", &Lang::EN), "Mar 1, 2020");
+        assert_eq!(locale::format_date("2020-03-01", &Lang::FR), "1er mars 2020");
+        assert_eq!(locale::format_date("2021-12-25", &Lang::FR), "25 déc. 2021");
+        assert_eq!(locale::format_date("not a date", &Lang::EN), "not a date");
+    }
+}
@@ -5,13 +5,30 @@
 
 use std::error::Error;
 use std::env;
-use std::io::{self, Write};
-use std::convert::TryInto;
-use chrono::NaiveDate;
+use std::io::{self, BufRead, Write};
 use std::cmp::Reverse;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
 
 use crate::datastore::*;
-use crate::persistence;
+use crate::batch;
+use crate::diff::{self, Change};
+use crate::index::Index;
+use crate::ini::Ini;
+use crate::layout;
+use crate::merge::{self, Key, MergePlan, MergeReport};
+use crate::locale;
+use crate::messages::{self, Msg};
+use crate::ops;
+use crate::pager::{self, Nav, Pager};
+use crate::chart::{self, ChartKind, Series};
+use crate::csv::CsvFormat;
+use crate::persistence::{self, Stamp};
+use crate::province;
+use crate::rates::{self, Population, Rates};
+use crate::timeseries;
+use crate::validate;
+use crate::views::{self, Views};
 
 
 /// A configuration object for carrying command-line arguments and Environment variables into program memory.
@@ -31,127 +48,386 @@ use crate::persistence;
 /// let config = Config::new(env::args());
 /// assert_eq!(Lang::EN, config.language);
 /// ```
+/// 
+/// The defaults can be changed in the settings file (`tabledb.ini` in the project root), and the command-line
+/// arguments override both:
+/// 
+/// ```
+/// [general]
+/// language = fr
+/// page_size = 40
+/// 
+/// [files]
+/// input = covid19-download.csv
+/// output = datastore.csv
+/// 
+/// [display]
+/// sort = date, pruid
+/// 
+/// [queries]
+/// ontario-march = abbr=ON, date=2020-03-01..2020-03-31
+/// ```
+/// 
+/// ```
+/// my_project> cargo run -- fr --page-size 50 --sort numtoday
+/// ```
+#[derive(Clone, Debug, PartialEq)]
 pub struct Config {
     pub language: Lang,
     pub filename: String,
+    pub page_size: usize,
+    pub input: String,
+    pub output: String,
+    pub sort: Option<String>,
+    pub queries: BTreeMap<String, String>,
+    pub batch: Option<String>,
+    pub dry_run: bool,
+    pub serve: Option<u16>,
+    pub csv: CsvFormat,
 }
 
+/// The command-line options, with the setting each one sets (see `Config::set`), or `None` for the ones that
+/// take no value.
+pub const OPTIONS: [(&str, Option<&str>); 14] = [
+    ("--lang", Some("language")),
+    ("--page-size", Some("page_size")),
+    ("--input", Some("input")),
+    ("--output", Some("output")),
+    ("--sort", Some("sort")),
+    ("--batch", Some("batch")),
+    ("--dry-run", None),
+    ("--serve", Some("serve")),
+    ("--delimiter", Some("delimiter")),
+    ("--decimal", Some("decimal")),
+    ("--line-endings", Some("line_endings")),
+    ("--quote", Some("quote")),
+    ("--bom", Some("bom")),
+    ("--excel", None),
+];
+
 /// Language variant parameter for Config type.
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Lang {
     EN,
     FR,
 }
 
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            language: Lang::EN,
+            filename: String::from("covid19-download.csv"),
+            page_size: pager::DEFAULT_PAGE_SIZE,
+            input: String::from("covid19-download.csv"),
+            output: String::from("datastore.csv"),
+            sort: None,
+            queries: BTreeMap::new(),
+            batch: None,
+            dry_run: false,
+            serve: None,
+            csv: CsvFormat::default(),
+        }
+    }
+}
+
 impl Config {
-    /// A function for outputting a new Config struct based on command line arguments.
+    /// A function for outputting a new Config struct based on the settings file and command line arguments.
     pub fn new(mut args: env::Args) -> Result<Config, Box<dyn Error>> {
         args.next();
 
-        //If arg is found, set config language, else output error
-        let language = match args.next() {
-            Some(arg) => {match &arg[..] {"en" => Lang::EN, "fr" => Lang::FR, _ => return Err("Must supply a valid language (en/fr)".into())}},
-            None => return Err("Must supply a language option (en/fr)".into()),
+        //Start from the settings file if there is one, then apply the command-line overrides
+        let settings = persistence::load_settings(persistence::SETTINGS_FILE)?;
+        let mut config = match &settings {
+            Some(ini) => Config::from_settings(ini, persistence::SETTINGS_FILE)?,
+            None => Config::default(),
         };
+        let language_set = config.apply_args(args)?;
 
-        //Optional allowing filename input argument (Disabled)
-        /*let filename = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a filename string"),
-        };*/
+        //The language must come from one or the other
+        if !language_set && settings.is_none_or(|ini| ini.get("general", "language").is_none()) {
+            return Err(messages::both(Msg::ArgLanguageMissing, &[]).into());
+        }
 
-        //Return wrapped configuration struct (Default filename enabled)
-        Ok(Config {
-            language,
-            filename: String::from("covid19-download.csv"),
-        })
+        //Return wrapped configuration struct (starting on the source file)
+        config.filename = config.input.clone();
+        Ok(config)
+    }
+
+    /// A function for reading a Config struct from the settings of a settings file, checking every value.
+    /// 
+    /// The `[views]` section and the `view` setting of `[display]` are left to `persistence::load_views`.
+    pub fn from_settings(ini: &Ini, filename: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        for (section, key, value) in ini.settings() {
+            let known = match (section.as_str(), key.as_str()) {
+                ("general", "language") | ("general", "page_size") | ("files", "input") | ("files", "output") | ("display", "sort")
+                | ("csv", "delimiter") | ("csv", "decimal") | ("csv", "line_endings") | ("csv", "quote") | ("csv", "bom") => {
+                    if !config.set(key, value) {
+                        return Err(messages::both(Msg::SettingsValueInvalid, &[&filename, value, key, section]));
+                    }
+                    true
+                }
+                ("queries", name) => {
+                    if !views::valid_name(name) || split_condition(value).is_none() {
+                        return Err(messages::both(Msg::SettingsValueInvalid, &[&filename, value, key, section]));
+                    }
+                    config.queries.insert(name.to_string(), value.clone());
+                    true
+                }
+                ("display", "view") | ("views", _) => true,
+                _ => false,
+            };
+            if !known {
+                return Err(messages::both(Msg::SettingsUnknown, &[&filename, key, section]));
+            }
+        }
+        config.check_files()?;
+        Ok(config)
+    }
+
+    /// Applies command-line arguments: a language (`en` or `fr`) and options such as `--page-size 40`.
+    /// Returns true if the language was given.
+    /// 
+    /// `--batch FILE` (with `--dry-run` to only show what would change) and `--serve PORT` can only be given on
+    /// the command line.
+    pub fn apply_args(&mut self, mut args: impl Iterator<Item = String>) -> Result<bool, String> {
+        let mut language_set = false;
+        while let Some(arg) = args.next() {
+            let key = match arg.as_str() {
+                "en" | "fr" => {self.set("language", &arg); language_set = true; continue}
+                "--dry-run" => {self.dry_run = true; continue}
+                "--excel" => {self.csv = CsvFormat::excel(); continue}
+                _ => match OPTIONS.iter().find(|(option, _)| *option == arg) {
+                    Some((_, Some(key))) => *key,
+                    _ if arg.starts_with("--") => {
+                        let options: Vec<&str> = OPTIONS.iter().map(|(option, _)| *option).collect();
+                        return Err(messages::both(Msg::ArgUnknown, &[&arg, &options.join(", ")]));
+                    }
+                    _ => return Err(messages::both(Msg::ArgLanguageInvalid, &[])),
+                },
+            };
+            let value = args.next().ok_or_else(|| messages::both(Msg::ArgValueMissing, &[&arg]))?;
+            if !self.set(key, &value) {
+                return Err(messages::both(Msg::ArgValueInvalid, &[&value, &arg]));
+            }
+            language_set |= key == "language";
+        }
+        if self.dry_run && self.batch.is_none() {
+            return Err(messages::both(Msg::ArgDryRunWithoutBatch, &[]));
+        }
+        if self.serve.is_some() && self.batch.is_some() {
+            return Err(messages::both(Msg::ArgServeWithBatch, &[]));
+        }
+        self.check_files()?;
+        Ok(language_set)
+    }
+
+    /// Sets one of the settings shared by the settings file and the command line, returning false if the value isn't valid.
+    /// 
+    /// The page size must be 1-1000, the files must be named, and the sort is a list of column names or numbers
+    /// (stored as numbers for `display`). The options of the working file's format are checked by `CsvFormat::set`.
+    fn set(&mut self, key: &str, value: &str) -> bool {
+        let value = value.trim();
+        match key {
+            "language" => match value.to_lowercase().as_str() {
+                "en" => self.language = Lang::EN,
+                "fr" => self.language = Lang::FR,
+                _ => return false,
+            },
+            "page_size" => match value.parse::<usize>() {
+                Ok(size) if (1..=1000).contains(&size) => self.page_size = size,
+                _ => return false,
+            },
+            "input" | "output" | "batch" if value.is_empty() => return false,
+            "input" => self.input = value.to_string(),
+            "output" => self.output = value.to_string(),
+            "batch" => self.batch = Some(value.to_string()),
+            "serve" => match value.parse::<u16>() {
+                Ok(port) if port > 0 => self.serve = Some(port),
+                _ => return false,
+            },
+            "sort" => match views::parse_columns(value) {
+                Ok(columns) => self.sort = Some(columns.iter().map(|c| (c + 1).to_string()).collect::<Vec<String>>().join(", ")),
+                Err(_) => return false,
+            },
+            "delimiter" | "decimal" | "line_endings" | "quote" | "bom" => return self.csv.set(key, value),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Checks that the source and working files are different, so saving never overwrites the source download.
+    fn check_files(&self) -> Result<(), String> {
+        if self.input == self.output {
+            return Err(messages::both(Msg::ConfigSameFiles, &[&self.input]));
+        }
+        Ok(())
+    }
+
+    /// Returns the conditions of a saved query. Names are lowercased in the settings file, so the name can be
+    /// given in any case.
+    pub fn query(&self, name: &str) -> Option<&String> {
+        self.queries.get(&name.trim().to_lowercase())
     }
+
     pub fn change_lang(&mut self, lang: Lang) {
         self.language = lang;
     }
     pub fn change_file(&mut self, file: String) {
         self.filename = file;
     }
+    /// Returns the text of a catalog message in the configured language.
+    pub fn text(&self, msg: Msg) -> &'static str {
+        messages::text(&self.language, msg)
+    }
+    /// Returns the text of a catalog message in the configured language, with its placeholders filled in.
+    pub fn fill(&self, msg: Msg, args: &[&dyn std::fmt::Display]) -> String {
+        messages::fill(&self.language, msg, args)
+    }
 }
 
 /// Run the data paginating system to present the user with an interactable command-line UI.
 /// 
-/// The `display` function creates a `counter` variable for the purposes of tracking page cycles. The 
+/// The `display` function creates a `Pager` for the purposes of tracking the current page. The 
 /// displayed lines of text are a slice of the `data` vector which is over a different part of the 
-/// vector depending on `counter`. Lines are printed using the appropriate language output method 
+/// vector depending on the page. Lines are printed using the appropriate language output method 
 /// depending on the `Config` struct `language` state.
 /// 
-/// The user is prompted once the preample plus 50 lines have been printed, and they can leave the 
-/// program by entering 'q' or 'Q'.
-pub fn display(config: &Config, column_labels: &Header, data: &Vec<DataRow>) -> Result<(), Box<dyn Error>> {
-    let mut active_data = data.clone();
-    let line_count = active_data.iter().count();
-    let mut counter = 0;
+/// The user is prompted once a page of `config.page_size` lines has been printed, and can move to the next,
+/// previous, first, last or any numbered page, change the page size, or leave by entering 'q' or 'Q'.
+/// 
+/// Sorting is done on a vector of references into `data`, so the rows themselves are never copied.
+/// 
+/// The computed columns (per-capita rates, fatality ratio and active cases) are shown after the stored
+/// ones, and can be sorted by like them with numbers 10-13. `rates` should be built over the whole table
+/// even when `data` is only part of it, since active cases depend on the earlier rows of each province.
+/// 
+/// Only the columns of the current view in `views` are shown, in its order. Option "c" chooses other columns
+/// or a saved view, and can save the choice as a named view in the settings file for later sessions.
+/// 
+/// Option "e" exports every row shown (not only the current page), in the current order and columns, to a
+/// Markdown, HTML or LaTeX file with the column labels of the current language.
+/// 
+/// The rows start out sorted by `config.sort` if the settings or command line give a default sort.
+pub fn display(config: &Config, column_labels: &Header, data: &[DataRow], rates: &Rates, views: &mut Views) -> Result<(), Box<dyn Error>> {
+    let mut active_data: Vec<&DataRow> = data.iter().collect();
+    let mut pager = Pager::new(active_data.len(), config.page_size);
     let mut sorting = String::from("0");
     let mut rev = false;
+    if let Some(spec) = &config.sort {
+        if let Some(sorted_by) = sort_by_spec(&mut active_data, spec, config, rates) {
+            sorting = sorted_by;
+        }
+    }
 
     //Loop for paginating output
-    while line_count > 25*counter {
+    while pager.is_open() {
         //Line below clears console window
-        std::process::Command::new("cmd").args(&["/C","cls"]).status().expect("Failed to execute process (CLS)");
+        std::process::Command::new("cmd").args(["/C","cls"]).status().expect("Failed to execute process (CLS)");
 
         //Print header lines, including column labels
-        println!("Covid Data CLI App - Thomas Ivanov");
-        print!("{}\n", column_labels.output_lang(config));
-
-        //Use line count to determine how to split text for presentation
-        let page: &[DataRow] = &active_data[25*counter..{if 25*(counter+1) < line_count {25*(counter+1)} else {line_count}}];
+        println!("{}", config.text(Msg::Title));
+        let mut labels = column_labels.labels_lang(config);
+        labels.extend(rates::RATE_COLUMNS.iter().map(|c| c.to_string()));
+        let labels = views.pick(&labels);
 
+        //Use the pager to determine how to split text for presentation
+        let page: &[&DataRow] = &active_data[pager.range()];
 
-        //Format the collected lines for presentation, assemble them in structs (Vector used purely as intermediary), and print to screen
-        for line in page.iter() {
-            println!("{}", line.output_lang(config));
+        //Format the collected lines for presentation (column widths are measured from this page), and print to screen
+        let cells: Vec<Vec<String>> = page.iter().map(|line| views.pick(&[line.cells_lang(config), rates.cells(line, &config.language)].concat())).collect();
+        for line in layout::render_table(&labels, &cells) {
+            println!("{}", line);
         }
 
-        //Remove the 50 lines previously read from content collection, then increment counter
-        counter += 1;
-
-        //Process user input to determine whether to show next page, sort the output, or quit
-        println!("\nPage: {}\n", counter);
-        println!("\n[Press ENTER to read more, S to sort output by column, R to reverse output (asc-desc), or Q to go back]");
+        //Process user input to determine whether to move between pages, sort the output, or quit
+        println!("\n{}\n", pager.status(config));
+        println!("{}\n{}", config.text(Msg::PageHelp), config.text(Msg::DisplayHelp));
         let mut input = String::new();
         io::stdin().read_line(&mut input).expect("Failed to read line");
-        match input.to_lowercase().trim() {
-            "q" => {break},
-            "s" => {
-                        active_data = data.clone();
+        let nav = pager::parse(&input);
+        if pager.apply(&nav) {
+            continue;
+        }
+        match nav {
+            Nav::Other(key) if key == "s" => {
+                        active_data = data.iter().collect();
                         //Option "s" is for sorting the page output by a column. The columns chosen are sorted for simultaneously, in order.
-                        print!("\nChoose one or more columns (1-9, separated by commas) to sort for, in order of priority: ");
+                        print!("\n{}", config.text(Msg::SortPrompt));
                         io::stdout().flush().unwrap();
                         let mut input = String::new();
                         io::stdin().read_line(&mut input).expect("Failed to read line");
-                        let comma_sep: Vec<String> = input.split(",").map(|e| e.trim().to_string()).collect();
-
-                        let mut i = comma_sep.len();
-
-                        //loop for each column sorting
-                        while i > 0 {
-                            //"sorting" is a variable that tracks what column was most recently sorted by, for future reference
-                            sorting = comma_sep[i-1].clone();
-
-                            match sort(&mut active_data, &sorting, config) {
-                                Ok(()) => {
-                                    //"rev" is a variable that tracks whether the sorting order is reversed
-                                    rev = false;
-                                    //counter must be set to 0 to start displaying from page 1 again
-                                    counter = 0;
-                                },
-                                Err(e) => {
-                                    counter -= 1;
-                                }
-                            }
-                            i -= 1;
+
+                        //"sorting" is a variable that tracks what column was most recently sorted by, for future reference
+                        if let Some(sorted_by) = sort_by_spec(&mut active_data, &input, config, rates) {
+                            sorting = sorted_by;
+                            //"rev" is a variable that tracks whether the sorting order is reversed
+                            rev = false;
+                            //Start displaying from page 1 again
+                            pager.first();
                         }
                     },
-            "r" => {
+            Nav::Other(key) if key == "r" => {
                         //Option "r" accounts for the most recent sort and reverses it, or un-reverses it if already reversed
-                        reverse(&mut active_data, &sorting, &mut rev, config);
-                        counter = 0;
+                        if rates.sort(&mut active_data, &sorting, !rev) {
+                            rev = !rev;
+                        }
+                        else {
+                            reverse(&mut active_data, &sorting, &mut rev, config);
+                        }
+                        pager.first();
+                    },
+            Nav::Other(key) if key == "c" => {
+                        //Option "c" chooses the columns shown (by name or number, in order) or a saved view
+                        let columns: Vec<String> = views::COLUMNS.iter().enumerate().map(|(i, c)| format!("{} {}", i + 1, c)).collect();
+                        let saved: Vec<&str> = views.saved.keys().map(|k| k.as_str()).collect();
+                        print!("\n{}", config.fill(Msg::ColumnsPrompt, &[&columns.join(", "), &saved.join(", ")]));
+                        io::stdout().flush().unwrap();
+                        let mut input = String::new();
+                        io::stdin().read_line(&mut input).expect("Failed to read line");
+                        if input.trim().is_empty() {
+                            continue;
+                        }
+                        if let Err(column) = views.select(&input) {
+                            println!("{}\n{}", config.fill(Msg::ViewUnknownColumn, &[&column]), config.text(Msg::PressEnterBack));
+                            io::stdin().read_line(&mut input).expect("Failed to read line");
+                            continue;
+                        }
+
+                        //A new choice of columns can be saved under a name, and the view shown is remembered for next time
+                        if views.name.is_none() {
+                            print!("{}", config.text(Msg::ViewSavePrompt));
+                            io::stdout().flush().unwrap();
+                            let mut name = String::new();
+                            io::stdin().read_line(&mut name).expect("Failed to read line");
+                            let name = name.trim();
+                            if views::valid_name(name) {
+                                views.save(name);
+                            }
+                            else if !name.is_empty() {
+                                println!("{}\n{}", config.fill(Msg::ViewNameInvalid, &[&name]), config.text(Msg::PressEnterBack));
+                                io::stdin().read_line(&mut input).expect("Failed to read line");
+                            }
+                        }
+                        persistence::save_views(persistence::SETTINGS_FILE, views, config)?;
+                    },
+            Nav::Other(key) if key == "e" => {
+                        //Option "e" exports the rows shown, in the order and columns shown, to a document
+                        print!("\n{}", config.fill(Msg::ExportPrompt, &[&active_data.len()]));
+                        io::stdout().flush().unwrap();
+                        let mut filename = String::new();
+                        io::stdin().read_line(&mut filename).expect("Failed to read line");
+                        let filename = filename.trim();
+                        if filename.is_empty() {
+                            continue;
+                        }
+                        let cells: Vec<Vec<String>> = active_data.iter().map(|line| views.pick(&[line.cells_lang(config), rates.cells(line, &config.language)].concat())).collect();
+                        let message = match persistence::export_table(filename, config.text(Msg::Title), &labels, &cells, config) {
+                            Ok(()) => config.fill(Msg::Exported, &[&cells.len(), &filename]),
+                            Err(e) => e.to_string(),
+                        };
+                        println!("{}\n{}", message, config.text(Msg::PressEnterBack));
+                        io::stdin().read_line(&mut input).expect("Failed to read line");
                     },
             _ => {}
         }
@@ -160,158 +436,338 @@ pub fn display(config: &Config, column_labels: &Header, data: &Vec<DataRow>) ->
     Ok(())
 }
 
-/// Function for by mutating vector of data rows to sort it by some column
-fn sort(data: &mut Vec<DataRow>, sorting: &str, config: &Config) -> Result<(), Box<dyn Error>> {
+/// Run the paginating system on the time series of one province.
+/// 
+/// The `display_series` function finds the rows of the province given by `province` (a pruid, abbreviation
+/// or name in either language) through the indexes, builds its time series, and prints it page by page with the
+/// derived columns after the regular ones. Rows are always in date order, so there is no sorting option, but
+/// the pages can be navigated as in `display`.
+pub fn display_series(province: String, config: &Config, column_labels: &Header, data: &[DataRow], index: &Index) -> Result<(), Box<dyn Error>> {
+    let positions = province_rows(&province, config, index)?;
+    let series = timeseries::series(data, &positions, config)?;
+    let mut pager = Pager::new(series.len(), config.page_size);
+
+    //Loop for paginating output
+    while pager.is_open() {
+        //Line below clears console window
+        std::process::Command::new("cmd").args(["/C","cls"]).status().expect("Failed to execute process (CLS)");
+
+        //Print header lines, including column labels and the derived column labels
+        println!("{}", config.text(Msg::Title));
+        let labels = timeseries::series_labels(column_labels, config);
+
+        let page = &series[pager.range()];
+        let cells: Vec<Vec<String>> = page.iter().map(|line| line.cells(config)).collect();
+        for line in layout::render_table(&labels, &cells) {
+            println!("{}", line);
+        }
+
+        println!("\n{}\n", pager.status(config));
+        println!("{}", config.text(Msg::PageHelp));
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read line");
+        pager.apply(&pager::parse(&input));
+    }
+
+    Ok(())
+}
+
+/// Print a line or bar chart of a numeric column over date for one or more provinces.
+/// 
+/// The user is prompted for the column (e.g. numtoday, numdeaths, ratetotal), the provinces (pruids or
+/// names separated by commas) and the chart type. The chart is scaled to fit the values and drawn with
+/// one series per province.
+pub fn display_chart(config: &Config, data: &[DataRow], index: &Index) -> Result<(), Box<dyn Error>> {
+    print!("{}", config.text(Msg::ChartColumnPrompt));
+    io::stdout().flush().unwrap();
+    let mut column = String::new();
+    io::stdin().read_line(&mut column).expect("Failed to read line");
+    let column = column.trim().to_lowercase();
+
+    print!("{}", config.text(Msg::ChartProvincesPrompt));
+    io::stdout().flush().unwrap();
+    let mut provinces = String::new();
+    io::stdin().read_line(&mut provinces).expect("Failed to read line");
+
+    print!("{}", config.text(Msg::ChartKindPrompt));
+    io::stdout().flush().unwrap();
+    let mut kind = String::new();
+    io::stdin().read_line(&mut kind).expect("Failed to read line");
+    let kind = match kind.trim() {
+        "b" | "B" => ChartKind::Bar,
+        _ => ChartKind::Line,
+    };
+
+    let series = chart_series(&column, &provinces, config, data, index)?;
+
+    //Line below clears console window
+    std::process::Command::new("cmd").args(["/C","cls"]).status().expect("Failed to execute process (CLS)");
+    println!("{}", config.text(Msg::Title));
+    println!("{}\n", config.fill(Msg::ChartTitle, &[&column]));
+    print!("{}", chart::chart(kind, &series, 20, 100));
+
+    println!("\n{}", config.text(Msg::PressEnterBack));
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Failed to read line");
+    Ok(())
+}
+
+/// Collects the (date, value) points of a numeric column for each province in a comma-separated list.
+fn chart_series(column: &str, provinces: &str, config: &Config, data: &[DataRow], index: &Index) -> Result<Vec<Series>, Box<dyn Error>> {
+    let mut series: Vec<Series> = Vec::new();
+    for province in provinces.split(',').filter(|p| !p.trim().is_empty()) {
+        let positions = province_rows(province, config, index)?;
+        let mut points: Vec<(String, f64)> = Vec::new();
+        for &pos in positions.iter() {
+            let value = data[pos].numeric(column).ok_or_else(|| config.fill(Msg::ChartNotNumeric, &[&column]))?;
+            points.push((data[pos].date.clone(), value));
+        }
+        let first = &data[positions[0]];
+        let label = match config.language {Lang::EN => first.prname.clone(), Lang::FR => first.prname_fr.clone()};
+        series.push(Series { label, points });
+    }
+    if series.is_empty() {
+        return Err(config.text(Msg::ChartNoProvinces).into());
+    }
+    Ok(series)
+}
+
+/// Print one line per province with its latest totals and a sparkline of `numtoday` over time.
+pub fn display_summary(config: &Config, data: &[DataRow], index: &Index) -> Result<(), Box<dyn Error>> {
+    //Line below clears console window
+    std::process::Command::new("cmd").args(["/C","cls"]).status().expect("Failed to execute process (CLS)");
+    println!("{}", config.text(Msg::Title));
+
+    let labels: Vec<String> = ["pruid", match config.language {Lang::EN => "prname", Lang::FR => "prnameFR"}, config.text(Msg::SummaryRows), "date", "numtotal", "numdeaths", "numtoday"]
+                                    .iter().map(|l| l.to_string()).collect();
+    let mut cells: Vec<Vec<String>> = Vec::new();
+
+    for pruid in index.pruids() {
+        let mut positions = index.pruid(pruid);
+        positions.sort_by(|a, b| data[*a].date.cmp(&data[*b].date));
+        let latest = &data[positions[positions.len() - 1]];
+        let today: Vec<f64> = positions.iter().map(|p| data[*p].numtoday as f64).collect();
+
+        cells.push(vec![
+            pruid.to_string(),
+            match config.language {Lang::EN => latest.prname.clone(), Lang::FR => latest.prname_fr.clone()},
+            positions.len().to_string(),
+            locale::format_date(&latest.date, &config.language),
+            locale::format_int(latest.numtotal, &config.language),
+            locale::format_int(latest.numdeaths, &config.language),
+            chart::sparkline(&today),
+        ]);
+    }
+    for line in layout::render_table(&labels, &cells) {
+        println!("{}", line);
+    }
+
+    println!("\n{}", config.text(Msg::PressEnterBack));
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Failed to read line");
+    Ok(())
+}
+
+/// Finds the rows of a province given by pruid, abbreviation or name (in either language) through the indexes.
+fn province_rows(province: &str, config: &Config, index: &Index) -> Result<Vec<usize>, Box<dyn Error>> {
+    let province = province.trim();
+    let positions = match province::lookup(province) {
+        Some(p) => index.pruid(p.pruid),
+        None => index.prname(province),
+    };
+    if positions.is_empty() {
+        return Err(config.fill(Msg::ProvinceNotFound, &[&province]).into());
+    }
+    Ok(positions)
+}
+
+/// Print every consistency problem found in the data, with the row numbers to use for editing them.
+/// 
+/// Returns the number of problems found so callers (such as the save option) can decide whether to go on.
+pub fn display_violations(config: &Config, data: &[DataRow]) -> usize {
+    let violations = validate::validate(data);
+    for violation in violations.iter() {
+        println!("{}", violation.describe(&config.language));
+    }
+    println!("\n{}", config.fill(Msg::ProblemsFound, &[&violations.len(), &data.len()]));
+    violations.len()
+}
+
+/// Sorts rows by a comma-separated list of column numbers (1-13), in order of priority. Returns the column
+/// sorted by last (the first one listed), or `None` if none of the numbers was a valid column.
+fn sort_by_spec(data: &mut [&DataRow], spec: &str, config: &Config, rates: &Rates) -> Option<String> {
+    let mut sorted_by = None;
+
+    //The columns are sorted by from the lowest priority up, since each sort keeps the order of equal rows
+    for sorting in spec.split(',').map(|e| e.trim()).rev() {
+        //Computed columns are sorted by the rates, stored columns by sort()
+        if rates.sort(data, sorting, false) || sort(data, sorting, config).is_ok() {
+            sorted_by = Some(sorting.to_string());
+        }
+    }
+    sorted_by
+}
+
+/// Function for by mutating vector of data rows to sort it by some column (numbered 1-9, as in `views::COLUMNS`)
+/// 
+/// The rows can be owned or borrowed (`Vec<DataRow>` or `Vec<&DataRow>`), so callers that only need a
+/// sorted view of the data don't have to clone it.
+pub fn sort<T: Borrow<DataRow>>(data: &mut [T], sorting: &str, config: &Config) -> Result<(), Box<dyn Error>> {
     match sorting {
         "1" =>  {
                     //sort_by_key is a built-in function that sorts a vector of structs by field
-                    //It mutates the vector, so that's why I clone the original vector at the top of this function
-                    data.sort_by_key(|r| r.pruid);
+                    //It mutates the vector, so display() sorts a vector of references rather than the rows themselves
+                    data.sort_by_key(|r| row(r).pruid);
                 }
         "2" =>  {
-                    data.sort_by_key(|r| if config.language == Lang::EN {r.prname.clone()} else {r.prname_fr.clone()});
+                    data.sort_by_key(|r| if config.language == Lang::EN {row(r).prname.clone()} else {row(r).prname_fr.clone()});
                 }
         "3" =>  {
-                    data.sort_by_key(|r| r.date.clone());
+                    data.sort_by_key(|r| row(r).date.clone());
                 }
         "4" =>  {
-                    data.sort_by_key(|r| r.numconf);
+                    data.sort_by_key(|r| row(r).numconf);
                 }
         "5" =>  {
-                    data.sort_by_key(|r| r.numprob);
+                    data.sort_by_key(|r| row(r).numprob);
                 }
         "6" =>  {
-                    data.sort_by_key(|r| r.numdeaths);
+                    data.sort_by_key(|r| row(r).numdeaths);
                 }
         "7" =>  {
-                    data.sort_by_key(|r| r.numtotal);
+                    data.sort_by_key(|r| row(r).numtotal);
                 }
         "8" =>  {
-                    data.sort_by_key(|r| r.numtoday);
+                    data.sort_by_key(|r| row(r).numtoday);
                 }
         "9" =>  {
-                    data.sort_by(|r, s| r.ratetotal.partial_cmp(&s.ratetotal).unwrap());
+                    data.sort_by(|r, s| row(r).ratetotal.partial_cmp(&row(s).ratetotal).unwrap());
                 }
-        _   =>  {println!("Please select a valid number (1-9)");return Err("".into())}
+        _   =>  {println!("{}", config.text(Msg::SortInvalid));return Err("".into())}
     };
     Ok(())
 }
 
 /// Function for reversing the order of the current column sort by mutating vector of data rows
-fn reverse(data: &mut Vec<DataRow>, sorting: &str, rev: &mut bool, config: &Config) {
+fn reverse<T: Borrow<DataRow>>(data: &mut [T], sorting: &str, rev: &mut bool, config: &Config) {
     match sorting {
         "1" =>  {
                     //Firstly matches the most recent sorting method by checking the "sorting" variable
-                    if *rev == false {
+                    if !*rev {
                         //If output is not reversed already for the current sorting option, reverses it 
-                        data.sort_by_key(|r| Reverse(r.pruid));
+                        data.sort_by_key(|r| Reverse(row(r).pruid));
                         *rev = true;
                     }
                     else {
                         //If output is reversed already for this sorting option, reverses it back to forward order
-                        data.sort_by_key(|r| r.pruid);
+                        data.sort_by_key(|r| row(r).pruid);
                         *rev = false;
                     }
                 }
         "2" =>  {
-                    if *rev == false {
-                        data.sort_by_key(|r| if config.language == Lang::EN {Reverse(r.prname.clone())} else {Reverse(r.prname_fr.clone())});
+                    if !*rev {
+                        data.sort_by_key(|r| if config.language == Lang::EN {Reverse(row(r).prname.clone())} else {Reverse(row(r).prname_fr.clone())});
                         *rev = true;
                     }
                     else {
-                        data.sort_by_key(|r| if config.language == Lang::EN {r.prname.clone()} else {r.prname_fr.clone()});
+                        data.sort_by_key(|r| if config.language == Lang::EN {row(r).prname.clone()} else {row(r).prname_fr.clone()});
                         *rev = false;
                     }
                 }
         "3" =>  {
-                    if *rev == false {
-                        data.sort_by_key(|r| Reverse(r.date.clone()));
+                    if !*rev {
+                        data.sort_by_key(|r| Reverse(row(r).date.clone()));
                         *rev = true;
                     }
                     else {
-                        data.sort_by_key(|r| r.date.clone());
+                        data.sort_by_key(|r| row(r).date.clone());
                         *rev = false;
                     }
                 }
         "4" =>  {
-                    if *rev == false {
-                        data.sort_by_key(|r| Reverse(r.numconf));
+                    if !*rev {
+                        data.sort_by_key(|r| Reverse(row(r).numconf));
                         *rev = true;
                     }
                     else {
-                        data.sort_by_key(|r| r.numconf);
+                        data.sort_by_key(|r| row(r).numconf);
                         *rev = false;
                     }
                 }
         "5" =>  {
-                    if *rev == false {
-                        data.sort_by_key(|r| Reverse(r.numprob));
+                    if !*rev {
+                        data.sort_by_key(|r| Reverse(row(r).numprob));
                         *rev = true;
                     }
                     else {
-                        data.sort_by_key(|r| r.numprob);
+                        data.sort_by_key(|r| row(r).numprob);
                         *rev = false;
                     }
                 }
         "6" =>  {
-                    if *rev == false {
-                        data.sort_by_key(|r| Reverse(r.numdeaths));
+                    if !*rev {
+                        data.sort_by_key(|r| Reverse(row(r).numdeaths));
                         *rev = true;
                     }
                     else {
-                        data.sort_by_key(|r| r.numdeaths);
+                        data.sort_by_key(|r| row(r).numdeaths);
                         *rev = false;
                     }
                 }
         "7" =>  {
-                    if *rev == false {
-                        data.sort_by_key(|r| Reverse(r.numtotal));
+                    if !*rev {
+                        data.sort_by_key(|r| Reverse(row(r).numtotal));
                         *rev = true;
                     }
                     else {
-                        data.sort_by_key(|r| r.numtotal);
+                        data.sort_by_key(|r| row(r).numtotal);
                         *rev = false;
                     }
                 }
         "8" =>  {
-                    if *rev == false {
-                        data.sort_by_key(|r| Reverse(r.numtoday));
+                    if !*rev {
+                        data.sort_by_key(|r| Reverse(row(r).numtoday));
                         *rev = true;
                     }
                     else {
-                        data.sort_by_key(|r| r.numtoday);
+                        data.sort_by_key(|r| row(r).numtoday);
                         *rev = false;
                     }
                 }
         "9" =>  { 
-                    if *rev == false {
-                        data.sort_by(|r, s| Reverse(r.ratetotal).partial_cmp(&Reverse(s.ratetotal)).unwrap());
+                    if !*rev {
+                        data.sort_by(|r, s| Reverse(row(r).ratetotal).partial_cmp(&Reverse(row(s).ratetotal)).unwrap());
                         *rev = true;
                     }
                     else {
-                        data.sort_by(|r, s| r.ratetotal.partial_cmp(&s.ratetotal).unwrap());
+                        data.sort_by(|r, s| row(r).ratetotal.partial_cmp(&row(s).ratetotal).unwrap());
                         *rev = false;
                     }
                 }
         _   =>  { 
-                    if *rev == false {
-                        data.sort_by_key(|r| Reverse(r.date.clone()));
+                    if !*rev {
+                        data.sort_by_key(|r| Reverse(row(r).date.clone()));
                         *rev = true;
                     }
                     else {
-                        data.sort_by_key(|r| r.date.clone());
+                        data.sort_by_key(|r| row(r).date.clone());
                         *rev = false;
                     }
                 }
     };
 }
 
+/// Returns the data row behind an owned or borrowed vector element, for use in sorting closures.
+fn row<T: Borrow<DataRow>>(r: &T) -> &DataRow {
+    r.borrow()
+}
+
 /// Search for a specific set of data rows by index on the set provided
 /// 
 /// The 'search' function takes a search string along with the configuration data, header and data to 
 /// filter, and parses the search string using both commas for enumerated indices, and dashes to
 /// denote a range of indices. The set of matching indices is returned as a vector to use when needed.
-pub fn search(search_index: String, config: &Config, data: &Vec<DataRow>) -> Result<Vec<usize>, Box<dyn Error>> {
+pub fn search(search_index: String, config: &Config, data: &[DataRow]) -> Result<Vec<usize>, Box<dyn Error>> {
     //final_data is the filtered list of data row indices we want to display from
     let mut final_data: Vec<usize> = Vec::new();
     
@@ -325,7 +781,7 @@ pub fn search(search_index: String, config: &Config, data: &Vec<DataRow>) -> Res
     for index in dash_sep {
         //If sub-vector is length 1 it didn't have a dash so doesn't denote a range - grab one data row only
         if index.len() == 1 {
-            let index = index.iter().next().unwrap().trim();
+            let index = index[0].trim();
             final_data.push(index.parse::<usize>()?);
         }
         //If sub-vector is length 1 it had dash to denote a range - grab a range of data rows
@@ -341,141 +797,410 @@ pub fn search(search_index: String, config: &Config, data: &Vec<DataRow>) -> Res
         }
         //Any length of sub-vector other than 1 or 2 is invalid
         else {
-            return Err("Search string parameter invalid. Please refer to the following example for formatting (without quotes):
-                            \"4, 7, 9-14\"\n".into())
+            return Err(config.text(Msg::SearchInvalid).into())
         }
     }
 
     Ok(final_data)
 }
 
-pub fn edit(search_index: String, config: &Config, column_labels: &Header, data: &mut Vec<DataRow>) -> Result<(), Box<dyn Error>> {
+/// Search for a specific set of data rows by field value using the secondary indexes
+/// 
+/// The 'query' function takes a comma-separated list of conditions and returns the indices of the data
+/// rows matching all of them, in table order. `pruid=35`, `prname=Ontario` (either language) and `abbr=ON`
+/// use the hash indexes, and `date=2020-03-01` or `date=2020-03-01..2020-03-31` use the ordered date index.
+/// 
+/// Any numeric column, stored or computed (e.g. `numtoday>100` or `cases100k>=50`), can also be compared
+/// with `=`, `!=`, `<`, `<=`, `>` or `>=`, and so can `date` (e.g. `date<2020-03-01`). These conditions are
+/// checked against every row.
+pub fn query(query: String, config: &Config, data: &[DataRow], index: &Index, rates: &Rates) -> Result<Vec<usize>, Box<dyn Error>> {
+    let mut final_data: Option<Vec<usize>> = None;
+
+    for condition in query.split(',') {
+        let (field, op, value) = match split_condition(condition) {
+            Some(parts) => parts,
+            None => return Err(config.text(Msg::QueryInvalid).into()),
+        };
+        let indexed = op == "=" && ["pruid", "prname", "prname_fr", "abbr", "date"].contains(&field);
+        let mut matches = if !indexed { scan(field, op, value, config, data, rates)? } else { match field {
+            "pruid" => index.pruid(value.parse::<isize>()?),
+            "prname" | "prname_fr" => index.prname(value),
+            "abbr" => match province::by_abbr(value) {
+                Some(p) => index.pruid(p.pruid),
+                None => return Err(config.fill(Msg::QueryUnknownAbbr, &[&value]).into()),
+            },
+            "date" => match value.split_once("..") {
+                Some((from, to)) => index.date_range(from.trim(), to.trim()),
+                None => index.date_range(value, value),
+            },
+            _ => return Err(config.fill(Msg::QueryBadColumn, &[&field]).into()),
+        }};
+        matches.sort_unstable();
+
+        //Each condition narrows down the rows matched by the conditions before it
+        final_data = Some(match final_data {
+            None => matches,
+            Some(previous) => previous.into_iter().filter(|i| matches.binary_search(i).is_ok()).collect(),
+        });
+    }
+
+    Ok(final_data.unwrap_or_default())
+}
+
+/// Splits a query condition such as "numtoday>=100" into its column, comparison operator and value.
+pub fn split_condition(condition: &str) -> Option<(&str, &str, &str)> {
+    //Two-character operators are tried first so ">=" isn't read as ">"
+    for op in ["!=", "<=", ">=", "=", "<", ">"] {
+        if let Some((field, value)) = condition.split_once(op) {
+            return Some((field.trim(), op, value.trim()));
+        }
+    }
+    None
+}
+
+/// Compares a numeric column (stored or computed) or the date of every row against a value, returning the matching row indices.
+fn scan(field: &str, op: &str, value: &str, config: &Config, data: &[DataRow], rates: &Rates) -> Result<Vec<usize>, Box<dyn Error>> {
+    //Dates are YYYY-MM-DD, so comparing them as text puts them in date order
+    if field == "date" {
+        return Ok(data.iter().enumerate().filter(|(_, row)| compare(row.date.as_str(), op, value)).map(|(i, _)| i).collect());
+    }
+    let value = value.parse::<f64>().map_err(|_| config.fill(Msg::QueryNotNumber, &[&field, &value]))?;
+    if !data.is_empty() && data[0].numeric(field).is_none() && !rates::RATE_COLUMNS.contains(&field) {
+        return Err(config.fill(Msg::QueryNotNumericColumn, &[&field]).into());
+    }
+
+    let mut matches: Vec<usize> = Vec::new();
+    for (i, row) in data.iter().enumerate() {
+        let v = match row.numeric(field).or_else(|| rates.value(row, field)) {
+            Some(v) => v,
+            None => continue,
+        };
+        if compare(v, op, value) {
+            matches.push(i);
+        }
+    }
+    Ok(matches)
+}
+
+/// Compares two values with a query comparison operator.
+fn compare<T: PartialOrd>(v: T, op: &str, value: T) -> bool {
+    match op {
+        "=" => v == value,
+        "!=" => v != value,
+        "<" => v < value,
+        "<=" => v <= value,
+        ">" => v > value,
+        _ => v >= value,
+    }
+}
+
+/// Edit a data row interactively in the terminal (see `edit_with`).
+pub fn edit(search_index: String, config: &Config, column_labels: &Header, data: &mut [DataRow], index: &mut Index, edited: &mut BTreeSet<Key>) -> Result<(), Box<dyn Error>> {
+    edit_with(&mut io::stdin().lock(), &mut io::stdout(), search_index, config, column_labels, data, index, edited)
+}
+
+/// Edit the fields of a data row one at a time, reading the user's choices from `input` and writing the prompts to `output`.
+/// 
+/// The user picks a column (1-10) and types its new value, which is checked and written by `ops::edit_row`.
+/// A refused value is explained and the column can be chosen again. Editing stops at Q or the end of the input.
+#[allow(clippy::too_many_arguments)]
+pub fn edit_with<R: BufRead, W: Write>(input: &mut R, output: &mut W, search_index: String, config: &Config, column_labels: &Header,
+                                       data: &mut [DataRow], index: &mut Index, edited: &mut BTreeSet<Key>) -> Result<(), Box<dyn Error>> {
     //The index of the data row we want to edit
     let search_index = search_index.trim().parse::<usize>()?;
-    if search_index < data.len() && data.len() != 0 {
-        loop {
-            let mut input = String::new();
-            println!("Choose a column (1-10) to edit from the following (row {}). Enter Q to quit.\n{}\n{}", 
-                                                        search_index.to_string(), column_labels.output_all(), data[search_index].output_all());
+    if search_index >= data.len() {
+        return Ok(());
+    }
+    loop {
+        writeln!(output, "{}\n{}", config.fill(Msg::EditPrompt, &[&search_index]),
+                                    layout::render_table(&column_labels.labels, &[data[search_index].public_vec()]).join("\n"))?;
+        //The number of the column whose data we want to edit
+        let column = match read_line(input)? {
+            Some(line) => line,
+            None => break,
+        };
+        let prompt = match column.as_str() {
+            "q" | "Q" => break,
+            "1" => Msg::EditPruidPrompt,
+            "2" | "3" => Msg::EditNamePrompt,
+            "4" => Msg::EditDatePrompt,
+            "5" | "6" | "7" | "8" | "9" => Msg::EditCountPrompt,
+            "10" => Msg::EditRatePrompt,
+            _ => {writeln!(output, "{}", config.text(Msg::EditColumnInvalid))?;continue}
+        };
+        writeln!(output, "{}", config.text(prompt))?;
+        let value = match read_line(input)? {
+            Some(line) => line,
+            None => break,
+        };
+        //Newly input column data is validated, then overwritten if valid (resets the loop if invalid, skipping the overwriting)
+        if let Err(e) = ops::edit_row(data, index, edited, search_index, &column, &value) {
+            writeln!(output, "{}", e.describe(&config.language))?;
+            continue;
+        }
+        writeln!(output, "{}", config.text(Msg::EditKeepGoing))?;
+        //Quit the user interaction editing loop
+        match read_line(input)?.as_deref() {
+            Some("q") | Some("Q") | None => break,
+            _ => continue
+        }
+    }
+    Ok(())
+}
+
+/// Delete a data row interactively in the terminal (see `delete_with`).
+pub fn delete(search_index: String, config: &Config, column_labels: &Header, data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>) -> Result<(), Box<dyn Error>> {
+    delete_with(&mut io::stdin().lock(), &mut io::stdout(), search_index, config, column_labels, data, index, edited)
+}
+
+/// Delete a data row after the user confirms it, reading the answer from `input` and writing the prompt to `output`.
+#[allow(clippy::too_many_arguments)]
+pub fn delete_with<R: BufRead, W: Write>(input: &mut R, output: &mut W, search_index: String, config: &Config, column_labels: &Header,
+                                         data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>) -> Result<(), Box<dyn Error>> {
+    //The data row index to delete
+    let search_index = search_index.trim().parse::<usize>()?;
+    if search_index < data.len() {
+        writeln!(output, "{}\n{}", config.fill(Msg::DeletePrompt, &[&search_index]),
+                                    data[search_index].output_lang(config))?;
+        //Removing the data from the vector of all the data in memory
+        if messages::is_yes(&read_line(input)?.unwrap_or_default()) {
+            ops::delete_rows(data, index, edited, &[search_index])?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a line of user input without its line break, or `None` at the end of the input.
+fn read_line<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+/// Apply a batch edit file to the table, all or nothing, and return the changes it made.
+/// 
+/// The commands (see `batch::parse`) are run on a copy of the table, which replaces the table only if every
+/// command succeeded and `dry_run` is false. Either way, the returned changes are what the file does.
+#[allow(clippy::too_many_arguments)]
+pub fn run_batch(filename: &str, dry_run: bool, config: &Config, population: &Population,
+                 data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>) -> Result<Vec<Change>, Box<dyn Error>> {
+    let commands = persistence::load_batch(filename, config)?;
+    let (mut new_data, mut new_index, mut new_edited) = (data.clone(), index.clone(), edited.clone());
+    batch::run(&commands, config, population, &mut new_data, &mut new_index, &mut new_edited)?;
+
+    let changes = diff::diff(data, &new_data);
+    if !dry_run {
+        *data = new_data;
+        *index = new_index;
+        *edited = new_edited;
+    }
+    Ok(changes)
+}
+
+/// Print the changes made by a batch edit file as a diff table, followed by a summary.
+pub fn print_changes(filename: &str, changes: &[Change], config: &Config, column_labels: &Header) {
+    for line in layout::render_table(&diff::table_labels(config), &diff::table(changes, &column_labels.labels, config)) {
+        println!("{}", line);
+    }
+    let (added, removed, modified) = diff::counts(changes);
+    println!("\n{}", config.fill(Msg::BatchSummary, &[&filename, &added, &removed, &modified]));
+}
+
+/// Apply a batch edit file from the menu. What the file would change is shown first, and the changes are
+/// only made if the user confirms them. A file with an error is reported without changing anything.
+pub fn batch_edit(config: &Config, column_labels: &Header, population: &Population,
+                  data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>) -> Result<(), Box<dyn Error>> {
+    print!("{}", config.text(Msg::BatchFilePrompt));
+    io::stdout().flush().unwrap();
+    let mut filename = String::new();
+    io::stdin().read_line(&mut filename).expect("Failed to read line");
+    let filename = filename.trim();
+    if filename.is_empty() {
+        return Ok(());
+    }
+
+    let mut input = String::new();
+    match run_batch(filename, true, config, population, data, index, edited) {
+        Ok(changes) => {
+            print_changes(filename, &changes, config, column_labels);
+            println!("{}", config.text(Msg::BatchApplyPrompt));
             io::stdin().read_line(&mut input).expect("Failed to read line");
-            let input = input.trim();
-            //The number of the column whose data we want to edit
-            match input {
-                "1" =>  {
-                            let mut input = String::new();
-                            println!("Choose a new value for this line (max 3 digits)");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
-                            let input = input.trim().parse::<usize>()?;
-                            //Newly input column data is validated, then overwritten if valid (resets the loop if invalid, skipping the overwriting)
-                            if input > 999 {println!("Invalid number (must be 0-999), please try again.");continue}
-                            data[search_index].pruid = input.try_into().unwrap();
-                        }
-                "2" =>  {
-                            let mut input = String::new();
-                            println!("Choose a new value for this line (max 30 characters)");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
-                            if input.len() > 30 {println!("Invalid name (must be less than 30 characters long), please try again.");continue}
-                            data[search_index].prname = String::from(input.trim());
-                        }
-                "3" =>  {
-                            let mut input = String::new();
-                            println!("Choose a new value for this line (max 30 characters)");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
-                            if input.len() > 30 {println!("Invalid name (must be less than 30 characters long), please try again.");continue}
-                            data[search_index].prname = String::from(input.trim());
-                        }
-                "4" =>  {
-                            let mut input = String::new();
-                            println!("Choose a new value for this line (max 15 characters)");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
-                            let input = input.trim();
-                            if input.len() > 30 {println!("Invalid date (must be less than 15 characters long), please try again.");continue}
-                            match NaiveDate::parse_from_str(&input, "%Y-%m-%d") {
-                                Ok(date) => {data[search_index].date = String::from(input);},
-                                Err(e) => {println!("Invalid date format, please try again.");continue},
-                            }
-                        }
-                "5" =>  {
-                            let mut input = String::new();
-                            println!("Choose a new value for this line (max 8 digits)");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
-                            let input = input.trim().parse::<usize>()?;
-                            if input > 99999999 {println!("Invalid number (must be 0-99999999), please try again.");continue}
-                            data[search_index].numconf = input.try_into().unwrap();
-                        }
-                "6" =>  {
-                            let mut input = String::new();
-                            println!("Choose a new value for this line (max 8 digits)");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
-                            let input = input.trim().parse::<usize>()?;
-                            if input > 99999999 {println!("Invalid number (must be 0-99999999), please try again.");continue}
-                            data[search_index].numprob = input.try_into().unwrap();
-                        }
-                "7" =>  {
-                            let mut input = String::new();
-                            println!("Choose a new value for this line (max 8 digits)");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
-                            let input = input.trim().parse::<usize>()?;
-                            if input > 99999999 {println!("Invalid number (must be 0-99999999), please try again.");continue}
-                            data[search_index].numdeaths = input.try_into().unwrap();
-                        }
-                "8" =>  {
-                            let mut input = String::new();
-                            println!("Choose a new value for this line (max 8 digits)");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
-                            let input = input.trim().parse::<usize>()?;
-                            if input > 99999999 {println!("Invalid number (must be 0-99999999), please try again.");continue}
-                            data[search_index].numtotal = input.try_into().unwrap();
-                        }
-                "9" =>  {
-                            let mut input = String::new();
-                            println!("Choose a new value for this line (max 8 digits)");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
-                            let input = input.trim().parse::<usize>()?;
-                            if input > 99999999 {println!("Invalid number (must be 0-99999999), please try again.");continue}
-                            data[search_index].numtoday = input.try_into().unwrap();
-                        }
-                "10" =>  {
-                            let mut input = String::new();
-                            println!("Choose a new value for this line (max 99999.99, no more than two digits after decimal point)");
-                            io::stdin().read_line(&mut input).expect("Failed to read line");
-                            let input = input.trim();
-                            let input_flt = input.parse::<f64>()?;
-                            if input_flt > 99999.99 {println!("Invalid number (must be 0.00-99999.99), please try again.");continue}
-                            let flt = input.match_indices('.').nth(0).map(|(index, _)| input.split_at(index)).unwrap();
-                            if flt.1.len()-1 > 2 {println!("Invalid number (must have no more than two digits after the decimal), please try again.");continue}
-                            data[search_index].ratetotal = input_flt;
-                        }
-                "q" | "Q" => break,
-                _   =>  {println!("Please select a valid number (1-9)");continue}
+            if !messages::is_yes(&input) {
+                return Ok(());
             }
+            run_batch(filename, false, config, population, data, index, edited)?;
+            println!("{}", config.text(Msg::BatchApplied));
+        }
+        Err(e) => println!("{}", e),
+    }
+    println!("{}", config.text(Msg::PressEnterBack));
+    io::stdin().read_line(&mut input).expect("Failed to read line");
+    Ok(())
+}
+
+/// Export the working data from the menu as an SQL script, to load into SQLite or PostgreSQL, or as an Arrow
+/// IPC file for analytics tools, depending on the extension of the file name. The file is an SQL script named
+/// after the working file unless the user gives another name, and either kind can be loaded back with `--input`.
+pub fn export_data(config: &Config, data: &[DataRow]) -> Result<(), Box<dyn Error>> {
+    let default = std::path::Path::new(&config.output).with_extension("sql").to_string_lossy().to_string();
+    print!("{}", config.fill(Msg::ExportDataPrompt, &[&default]));
+    io::stdout().flush().unwrap();
+    let mut filename = String::new();
+    io::stdin().read_line(&mut filename).expect("Failed to read line");
+    let filename = match filename.trim() {
+        "" => default.as_str(),
+        name => name,
+    };
+
+    let extension = std::path::Path::new(filename).extension().map(|e| e.to_string_lossy().to_lowercase());
+    let exported = match extension.as_deref() {
+        Some("sql") => persistence::save_sql(filename, data, config).map(|table| config.fill(Msg::SqlExported, &[&data.len(), &filename, &table])),
+        Some("arrow" | "feather" | "ipc") => persistence::save_arrow(filename, data, config).map(|_| config.fill(Msg::Exported, &[&data.len(), &filename])),
+        _ => Err(config.fill(Msg::ExportDataFormatUnknown, &[&filename]).into()),
+    };
+    let message = exported.unwrap_or_else(|e| e.to_string());
+    println!("{}\n{}", message, config.text(Msg::PressEnterBack));
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Failed to read line");
+    Ok(())
+}
+
+/// Merge a newer download of the source file into the working data, instead of replacing it like `refresh`.
+/// 
+/// Rows are matched by `pruid` and `date`. New rows are appended, and each changed row is shown field by
+/// field (old and new values) for the user to accept or reject. Rows that were edited or deleted locally
+/// (`edited`) are protected: they are listed as conflicts and the local version is kept. A summary of the
+/// merge is printed at the end and returned.
+pub fn merge(config: &Config, column_labels: &Header, data: &mut Vec<DataRow>, index: &mut Index, edited: &BTreeSet<Key>) -> Result<MergeReport, Box<dyn Error>> {
+    let plan = plan_merge(config, data, edited)?;
+    let header = vec![config.text(Msg::DiffColumn).to_string(), config.text(Msg::DiffOld).to_string(), config.text(Msg::DiffNew).to_string()];
+    let mut report = MergeReport {added: plan.added.len(), conflicts: plan.conflicts.len(), unchanged: plan.unchanged, ..MergeReport::default()};
+
+    //Changed rows are accepted or rejected one at a time, or all at once with "a"
+    let mut accept_all = false;
+    for (pos, new) in plan.changed.iter() {
+        let mut accept = accept_all;
+        if !accept_all {
+            //Line below clears console window
+            std::process::Command::new("cmd").args(["/C","cls"]).status().expect("Failed to execute process (CLS)");
+            println!("{}\n", config.text(Msg::Title));
+            println!("{}", config.fill(Msg::MergeChanged, &[pos, &new.pruid, &new.date, &config.input]));
+            for line in layout::render_table(&header, &merge::changed_fields(&column_labels.labels, &data[*pos], new)) {
+                println!("{}", line);
+            }
+            println!("\n{}", config.text(Msg::MergeAccept));
             let mut input = String::new();
-            println!("Do you want to keep editing? Enter Q to quit (any key to continue)");
             io::stdin().read_line(&mut input).expect("Failed to read line");
-            //Quit the user interaction editing loop
-            match input.trim() {
-                "q" | "Q" => break,
-                _ => continue
-            }
+            accept_all = input.trim().eq_ignore_ascii_case("a");
+            accept = accept_all || messages::is_yes(&input);
         }
+        if accept {
+            let old = std::mem::replace(&mut data[*pos], new.clone());
+            index.update(*pos, &old, &data[*pos]);
+            report.accepted += 1;
+        }
+        else {
+            report.rejected += 1;
+        }
+    }
 
+    for row in plan.added.into_iter() {
+        data.push(row);
+        index.insert(data.len() - 1, &data[data.len() - 1]);
     }
-    Ok(())
+
+    //Line below clears console window
+    std::process::Command::new("cmd").args(["/C","cls"]).status().expect("Failed to execute process (CLS)");
+    println!("{}\n", config.text(Msg::Title));
+    for (pos, new) in plan.conflicts.iter() {
+        match pos {
+            Some(pos) => {
+                println!("{}", config.fill(Msg::MergeConflict, &[pos, &new.pruid, &new.date, &config.input]));
+                for line in layout::render_table(&header, &merge::changed_fields(&column_labels.labels, &data[*pos], new)) {
+                    println!("{}", line);
+                }
+                println!();
+            }
+            None => println!("{}\n", config.fill(Msg::MergeConflictDeleted, &[&new.pruid, &new.date, &config.input])),
+        }
+    }
+    println!("{}", config.fill(Msg::MergeSummary, &[&config.input, &report.added, &report.accepted, &report.rejected, &report.conflicts, &report.unchanged]));
+    println!("\n{}", config.text(Msg::PressEnterBack));
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Failed to read line");
+    Ok(report)
 }
 
-pub fn delete(search_index: String, config: &Config, column_labels: &Header, data: &mut Vec<DataRow>) -> Result<(), Box<dyn Error>> {
-    //The data row index to delete
-    let search_index = search_index.trim().parse::<usize>()?;
-    if search_index < data.len() && data.len() != 0 {
+/// Plan the merge of the source download (`config.input`) into the working data with `merge::plan`. Every
+/// row of the download is read, not just the ones a first import takes.
+pub fn plan_merge(config: &Config, data: &[DataRow], edited: &BTreeSet<Key>) -> Result<MergePlan, Box<dyn Error>> {
+    let mut source_config = config.clone();
+    source_config.change_file(config.input.clone());
+    let (_, source) = persistence::load_all_data(&source_config)?;
+    Ok(merge::plan(data, &source, edited))
+}
+
+/// Load every row of two data files and compare them with `diff::diff`. The column labels are those of the old file.
+pub fn diff_files(config: &Config, old_file: &str, new_file: &str) -> Result<(Header, Vec<Change>), Box<dyn Error>> {
+    let mut file_config = config.clone();
+    file_config.change_file(old_file.to_string());
+    let (column_labels, old) = persistence::load_all_data(&file_config)?;
+    file_config.change_file(new_file.to_string());
+    let (_, new) = persistence::load_all_data(&file_config)?;
+    Ok((column_labels, diff::diff(&old, &new)))
+}
+
+/// Compare two data files and show the rows added, removed and modified between them, page by page.
+/// 
+/// The user is prompted for the old and new files (by default the working file and the source download),
+/// which can be either kind of data file. Rows are matched by `pruid` and `date`, and each changed field of a
+/// modified row is shown with its old and new values. The diff can then be exported to a CSV file.
+pub fn display_diff(config: &Config) -> Result<(), Box<dyn Error>> {
+    print!("{}", config.fill(Msg::DiffFilesPrompt, &[&config.output, &config.input]));
+    io::stdout().flush().unwrap();
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Failed to read line");
+    let (old_file, new_file) = match input.split_once(',') {
+        Some((old, new)) if !old.trim().is_empty() && !new.trim().is_empty() => (old.trim(), new.trim()),
+        None if input.trim().is_empty() => (config.output.as_str(), config.input.as_str()),
+        _ => return Err(config.text(Msg::DiffFilesInvalid).into()),
+    };
+
+    let (column_labels, changes) = diff_files(config, old_file, new_file)?;
+    let (added, removed, modified) = diff::counts(&changes);
+    let summary = config.fill(Msg::DiffSummary, &[&old_file, &new_file, &added, &removed, &modified]);
+    let header = diff::table_labels(config);
+    let rows = diff::table(&changes, &column_labels.labels, config);
+
+    //Loop for paginating output
+    let mut pager = Pager::new(rows.len(), config.page_size);
+    while pager.is_open() {
+        //Line below clears console window
+        std::process::Command::new("cmd").args(["/C","cls"]).status().expect("Failed to execute process (CLS)");
+        println!("{}\n{}\n", config.text(Msg::Title), summary);
+        for line in layout::render_table(&header, &rows[pager.range()]) {
+            println!("{}", line);
+        }
+        println!("\n{}\n", pager.status(config));
+        println!("{}", config.text(Msg::PageHelp));
         let mut input = String::new();
-        println!("Do you want to delete the following (row {})? y/N\n{}", 
-                                        search_index.to_string(), data[search_index].output_lang(config));
         io::stdin().read_line(&mut input).expect("Failed to read line");
-        let input = input.trim();
-        //Removing the data from the vector of all the data in memory
-        if (input == "Y") | (input == "y") {
-            data.remove(search_index);
-        }
+        pager.apply(&pager::parse(&input));
+    }
+
+    //Offer to export the diff, with values as stored in the files
+    println!("\n{}", summary);
+    print!("{}", config.text(Msg::DiffExportPrompt));
+    io::stdout().flush().unwrap();
+    let mut filename = String::new();
+    io::stdin().read_line(&mut filename).expect("Failed to read line");
+    let filename = filename.trim();
+    if !filename.is_empty() {
+        persistence::save_table(filename, &header, &rows)?;
+        println!("{}\n{}", config.fill(Msg::DiffExported, &[&filename]), config.text(Msg::PressEnterBack));
+        io::stdin().read_line(&mut input).expect("Failed to read line");
     }
     Ok(())
 }
@@ -486,8 +1211,123 @@ pub fn load(config: &Config) -> Result<(Header, Vec<DataRow>), Box<dyn Error>> {
     persistence::load_data(config)
 }
 
-pub fn save(column_labels: &Header, data: &Vec<DataRow>) -> Result<(), Box<dyn Error>> {
-    persistence::save_data(&column_labels, &data)
+/// A loaded table: its column labels, its rows and the stamp of the working file when it was loaded.
+pub type Stamped = (Header, Vec<DataRow>, Option<Stamp>);
+
+/// Load the data file named in the config along with the stamp of the working file, under a shared lock so
+/// another session can't be halfway through saving it.
+pub fn load_stamped(config: &Config) -> Result<Stamped, Box<dyn Error>> {
+    let _lock = persistence::lock_data(config, false)?;
+    read_stamped(config)
+}
+
+/// Load the data file named in the config along with the stamp of the working file. The caller holds a lock.
+fn read_stamped(config: &Config) -> Result<Stamped, Box<dyn Error>> {
+    let stamp = persistence::stamp(&config.output)?;
+    let (column_labels, data) = persistence::load_data(config)?;
+    Ok((column_labels, data, stamp))
+}
+
+/// Save the data and the locally edited rows to the working file under an exclusive lock, and move the stamp
+/// on to the saved file. Nothing is saved if another session saved the file since the stamp was taken.
+pub fn save(column_labels: &Header, data: &[DataRow], edited: &BTreeSet<Key>, config: &Config, stamp: &mut Option<Stamp>) -> Result<(), Box<dyn Error>> {
+    let _lock = persistence::lock_data(config, true)?;
+    write_stamped(column_labels, data, edited, config, stamp)
+}
+
+/// Does the work of `save`. The caller holds an exclusive lock.
+fn write_stamped(column_labels: &Header, data: &[DataRow], edited: &BTreeSet<Key>, config: &Config, stamp: &mut Option<Stamp>) -> Result<(), Box<dyn Error>> {
+    if persistence::changed_since(&config.output, stamp.as_ref())? {
+        return Err(config.fill(Msg::SaveConflict, &[&config.output]).into());
+    }
+    persistence::save_data(column_labels, data, config)?;
+    persistence::save_edits(edited, config)?;
+    *stamp = persistence::stamp(&config.output)?;
+    Ok(())
+}
+
+/// Merge what another session saved to the working file since the stamp was taken into the data, and move
+/// the stamp on to the merged file.
+///
+/// Rows are matched by `pruid` and `date` as in `merge`, but every change is taken without asking: new rows
+/// are added and changed rows replaced, except the rows edited or deleted here, which keep their local
+/// version. The rows the other session edited are added to `edited`.
+pub fn merge_saved(config: &Config, data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>, stamp: &mut Option<Stamp>) -> Result<MergeReport, Box<dyn Error>> {
+    let _lock = persistence::lock_data(config, false)?;
+    merge_read(config, data, index, edited, stamp)
+}
+
+/// Does the work of `merge_saved`. The caller holds a lock.
+fn merge_read(config: &Config, data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>, stamp: &mut Option<Stamp>) -> Result<MergeReport, Box<dyn Error>> {
+    let mut saved_config = config.clone();
+    saved_config.change_file(config.output.clone());
+    let (_, saved, saved_stamp) = read_stamped(&saved_config)?;
+
+    let plan = merge::plan(data, &saved, edited);
+    let report = MergeReport {added: plan.added.len(), accepted: plan.changed.len(), conflicts: plan.conflicts.len(), unchanged: plan.unchanged, ..MergeReport::default()};
+    for (pos, new) in plan.changed.into_iter() {
+        let old = std::mem::replace(&mut data[pos], new);
+        index.update(pos, &old, &data[pos]);
+    }
+    for row in plan.added.into_iter() {
+        data.push(row);
+        index.insert(data.len() - 1, &data[data.len() - 1]);
+    }
+    edited.extend(persistence::load_edits(config)?);
+    *stamp = saved_stamp;
+    Ok(report)
+}
+
+/// Save the data without asking, first merging in (as `merge_saved` does) whatever another session saved to
+/// the working file since the stamp was taken. The exclusive lock is held from the check through the write,
+/// so no other save can land in between.
+pub fn save_merged(column_labels: &Header, data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>, config: &Config, stamp: &mut Option<Stamp>) -> Result<(), Box<dyn Error>> {
+    let _lock = persistence::lock_data(config, true)?;
+    if persistence::changed_since(&config.output, stamp.as_ref())? {
+        merge_read(config, data, index, edited, stamp)?;
+    }
+    write_stamped(column_labels, data, edited, config, stamp)
+}
+
+/// Save the data from the menu. If another session saved the working file since the stamp was taken, the
+/// user chooses to reload it (dropping the changes made here), merge its changes in before saving,
+/// overwrite it, or cancel.
+pub fn save_checked(config: &mut Config, column_labels: &mut Header, data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>, stamp: &mut Option<Stamp>) -> Result<(), Box<dyn Error>> {
+    //Asked again if yet another save comes in while the user is choosing
+    while persistence::changed_since(&config.output, stamp.as_ref())? {
+        println!("{}", config.fill(Msg::SaveConflict, &[&config.output]));
+        print!("{}", config.text(Msg::SaveConflictPrompt));
+        io::stdout().flush().unwrap();
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read line");
+        match input.trim().to_lowercase().as_str() {
+            "r" => {
+                config.change_file(config.output.clone());
+                let (labels, rows, new_stamp) = load_stamped(config)?;
+                *column_labels = labels;
+                *data = rows;
+                *index = Index::new(data);
+                *edited = persistence::load_edits(config)?;
+                *stamp = new_stamp;
+                println!("{}\n{}", config.fill(Msg::SaveReloaded, &[&data.len(), &config.output]), config.text(Msg::PressEnterBack));
+                io::stdin().read_line(&mut input).expect("Failed to read line");
+                return Ok(());
+            }
+            "m" => {
+                let r = merge_saved(config, data, index, edited, stamp)?;
+                println!("{}", config.fill(Msg::MergeSummary, &[&config.output, &r.added, &r.accepted, &r.rejected, &r.conflicts, &r.unchanged]));
+                println!("{}", config.text(Msg::PressEnterBack));
+                io::stdin().read_line(&mut input).expect("Failed to read line");
+            }
+            "o" => *stamp = persistence::stamp(&config.output)?,
+            _ => {
+                println!("{}\n{}", config.text(Msg::SaveCancelled), config.text(Msg::PressEnterBack));
+                io::stdin().read_line(&mut input).expect("Failed to read line");
+                return Ok(());
+            }
+        }
+    }
+    save(column_labels, data, edited, config, stamp)
 }
 
 pub fn refresh(config: &mut Config) -> Result<(Header, Vec<DataRow>), Box<dyn Error>> {
@@ -496,17 +1336,21 @@ pub fn refresh(config: &mut Config) -> Result<(Header, Vec<DataRow>), Box<dyn Er
 
 #[cfg(test)]
 mod test {
+    use std::collections::BTreeSet;
+    use std::io::Cursor;
+
     use crate::logic;
+    use crate::diff::Change;
+    use crate::index::Index;
     use crate::persistence;
     use crate::datastore;
+    use crate::ini::Ini;
 
     #[test]
     fn test_refresh() {
-        let mut config = logic::Config {language: logic::Lang::EN, filename: String::from("covid19-download.csv")};
-        let data_original: Vec<datastore::DataRow>;
-
+        let mut config = logic::Config {language: logic::Lang::EN, filename: String::from("covid19-download.csv"), ..logic::Config::default()};
         let both = persistence::load_data(&config).unwrap();
-        data_original = both.1;
+        let data_original: Vec<datastore::DataRow> = both.1;
         let mut data = data_original.clone();
 
         data.remove(1);
@@ -519,11 +1363,9 @@ mod test {
 
     #[test]
     fn test_sort() {
-        let config = logic::Config {language: logic::Lang::EN, filename: String::from("covid19-download.csv")};
-        let data_original: Vec<datastore::DataRow>;
-
+        let config = logic::Config {language: logic::Lang::EN, filename: String::from("covid19-download.csv"), ..logic::Config::default()};
         let both = persistence::load_data(&config).unwrap();
-        data_original = both.1;
+        let data_original: Vec<datastore::DataRow> = both.1;
 
         let search_result = logic::search(String::from("0-3"), &config, &data_original).unwrap();
         let mut out: Vec<datastore::DataRow> = Vec::new();
@@ -553,4 +1395,184 @@ mod test {
         assert_eq!(data_sorted[1], data_reversed[1]);
         assert_eq!(data_sorted[2], data_reversed[0]);
     }
+
+    #[test]
+    fn test_saved_query_case() {
+        let ini = Ini::parse("[queries]\nOntario = abbr=ON\n").unwrap();
+        let config = logic::Config::from_settings(&ini, "tabledb.ini").unwrap();
+        assert_eq!(config.query("Ontario").map(|q| q.as_str()), Some("abbr=ON"));
+        assert_eq!(config.query(" ONTARIO\n").map(|q| q.as_str()), Some("abbr=ON"));
+        assert_eq!(config.query("quebec"), None);
+    }
+
+    #[test]
+    fn test_config_settings() {
+        let ini = Ini::parse("[general]\nlanguage = fr\npage_size = 40\n[files]\noutput = working.csv\n[display]\nsort = date, 1\nview = compact\n[queries]\nontario = abbr=ON\n").unwrap();
+        let mut config = logic::Config::from_settings(&ini, "tabledb.ini").unwrap();
+        assert_eq!(config.language, logic::Lang::FR);
+        assert_eq!((config.page_size, config.output.as_str()), (40, "working.csv"));
+        assert_eq!(config.sort.as_deref(), Some("3, 1"));
+        assert_eq!(config.queries.get("ontario").map(|q| q.as_str()), Some("abbr=ON"));
+
+        //Command-line arguments override the settings file
+        let args = ["en", "--page-size", "10", "--sort", "numtoday"].iter().map(|a| a.to_string());
+        assert!(config.apply_args(args).unwrap());
+        assert_eq!((config.language, config.page_size, config.sort.as_deref()), (logic::Lang::EN, 10, Some("8")));
+        assert!(!config.apply_args(["--input", "source.csv"].iter().map(|a| a.to_string())).unwrap());
+        config.apply_args(["--batch", "fixes.txt", "--dry-run"].iter().map(|a| a.to_string())).unwrap();
+        assert_eq!((config.batch.as_deref(), config.dry_run), (Some("fixes.txt"), true));
+        assert!(logic::Config::default().apply_args(["--serve", "8080"].iter().map(|a| a.to_string())).is_ok());
+        config.apply_args(["--excel", "--delimiter", "tab"].iter().map(|a| a.to_string())).unwrap();
+        assert_eq!((config.csv.delimiter, config.csv.decimal, config.csv.bom), ('\t', ',', true));
+        let ini = Ini::parse("[csv]\ndelimiter = ;\nquote = text\n").unwrap();
+        assert_eq!(logic::Config::from_settings(&ini, "tabledb.ini").unwrap().csv.delimiter, ';');
+
+        //Unknown settings, bad values and clashing files are reported
+        for text in ["[general]\ncolour = red", "[general]\npage_size = 0", "[display]\nsort = date, nothing", "[files]\ninput = a.csv\noutput = a.csv"] {
+            assert!(logic::Config::from_settings(&Ini::parse(text).unwrap(), "tabledb.ini").is_err(), "{}", text);
+        }
+        for args in [vec!["de"], vec!["--page-size"], vec!["--page-size", "many"], vec!["--colour", "red"], vec!["--dry-run"], vec!["--serve", "0"], vec!["--decimal", ";"]] {
+            assert!(logic::Config::default().apply_args(args.iter().map(|a| a.to_string())).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_unknown_option() {
+        //Every listed option is known, and all of them are named when an option isn't
+        for (option, key) in logic::OPTIONS.iter() {
+            let mut args = vec![option.to_string()];
+            args.extend(key.map(|_| String::from("x")));
+            let error = logic::Config::default().apply_args(args.into_iter()).err().unwrap_or_default();
+            assert!(!error.starts_with("Unknown option"), "{}", option);
+        }
+        let error = logic::Config::default().apply_args(["--colour"].iter().map(|a| a.to_string())).unwrap_err();
+        assert!(error.starts_with("Unknown option \"--colour\" (the options are --lang, --page-size,"), "{}", error);
+        assert!(logic::OPTIONS.iter().all(|(option, _)| error.contains(&format!("{},", option)) || error.contains(&format!("{})", option))));
+    }
+
+    #[test]
+    fn test_edit_prompts() {
+        let config = logic::Config::default();
+        let column_labels = datastore::Header::new(["pruid", "prname", "prnameFR", "date", "numconf", "numprob", "numdeaths", "numtotal", "numtoday", "ratetotal"]
+                                                    .iter().map(|l| l.to_string()).collect());
+        let mut data = vec![datastore::DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "1", "10", "10", "0.1"])];
+        let mut index = Index::new(&data);
+        let mut edited = BTreeSet::new();
+
+        //A refused value is explained and the column asked for again, then the edit is made and the user quits
+        let mut input = Cursor::new("9\nlots\n9\n412\nq\n");
+        let mut output: Vec<u8> = Vec::new();
+        logic::edit_with(&mut input, &mut output, String::from("0"), &config, &column_labels, &mut data, &mut index, &mut edited).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("numtoday: \"lots\" is not a whole number"), "{}", output);
+        assert_eq!((data[0].numtoday, edited.len()), (412, 1));
+
+        //The delete prompt defaults to no
+        logic::delete_with(&mut Cursor::new("\n"), &mut Vec::new(), String::from("0"), &config, &column_labels, &mut data, &mut index, &mut edited).unwrap();
+        assert_eq!(data.len(), 1);
+        logic::delete_with(&mut Cursor::new("y\n"), &mut Vec::new(), String::from("0"), &config, &column_labels, &mut data, &mut index, &mut edited).unwrap();
+        assert!(data.is_empty() && index.pruids().is_empty());
+    }
+
+    #[test]
+    fn test_save_conflict() {
+        let output = std::env::temp_dir().join(format!("tabledb-conflict-{}.csv", std::process::id()));
+        let config = logic::Config {output: output.to_str().unwrap().to_string(), ..logic::Config::default()};
+        let column_labels = datastore::Header::new(["pruid", "prname", "prnameFR", "date", "numconf", "numprob", "numdeaths", "numtotal", "numtoday", "ratetotal"]
+                                                    .iter().map(|l| l.to_string()).collect());
+        let row = |date: &str, total: &str| datastore::DataRow::new(vec!["35", "Ontario", "Ontario", date, total, "0", "0", total, "0", "0.1"]);
+        let mut data = vec![row("2020-03-01", "10"), row("2020-03-02", "12")];
+        let mut stamp = None;
+        logic::save(&column_labels, &data, &BTreeSet::new(), &config, &mut stamp).unwrap();
+
+        //Another session changes both rows and adds one, after this session edited the first row
+        let mut other_stamp = stamp;
+        let other = vec![row("2020-03-01", "11"), row("2020-03-02", "13"), row("2020-03-03", "15")];
+        logic::save(&column_labels, &other, &BTreeSet::new(), &config, &mut other_stamp).unwrap();
+        let mut index = Index::new(&data);
+        let mut edited = BTreeSet::new();
+        crate::ops::edit_row(&mut data, &mut index, &mut edited, 0, "numtoday", "4").unwrap();
+        assert!(logic::save(&column_labels, &data, &edited, &config, &mut stamp).is_err());
+
+        logic::save_merged(&column_labels, &mut data, &mut index, &mut edited, &config, &mut stamp).unwrap();
+        assert_eq!(data.iter().map(|r| (r.numconf, r.numtoday)).collect::<Vec<_>>(), vec![(10, 4), (13, 0), (15, 0)]);
+        assert_eq!(index, Index::new(&data));
+        assert_eq!(stamp, persistence::stamp(&config.output).unwrap());
+
+        for file in [config.output.clone(), persistence::edits_file(&config), persistence::lock_file(&config)] {
+            std::fs::remove_file(file).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_save_merged_waits_for_lock() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        static WAITED: AtomicBool = AtomicBool::new(false);
+
+        let output = std::env::temp_dir().join(format!("tabledb-merged-lock-{}.csv", std::process::id()));
+        let config = logic::Config {output: output.to_str().unwrap().to_string(), ..logic::Config::default()};
+        let column_labels = datastore::Header::new(["pruid", "prname", "prnameFR", "date", "numconf", "numprob", "numdeaths", "numtotal", "numtoday", "ratetotal"]
+                                                    .iter().map(|l| l.to_string()).collect());
+        let data = vec![datastore::DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "0", "10", "0", "0.1"])];
+        persistence::on_lock_wait(|_| WAITED.store(true, Ordering::SeqCst));
+
+        //Another session holds the lock, so nothing is checked or written until it lets go
+        let held = persistence::lock_data(&config, false).unwrap();
+        let saving = {
+            let (config, column_labels, mut data) = (config.clone(), column_labels, data);
+            std::thread::spawn(move || {
+                let mut index = Index::new(&data);
+                logic::save_merged(&column_labels, &mut data, &mut index, &mut BTreeSet::new(), &config, &mut None).unwrap();
+            })
+        };
+        while !WAITED.load(Ordering::SeqCst) {
+            std::thread::yield_now();
+        }
+        assert!(!output.exists());
+        drop(held);
+        saving.join().unwrap();
+        assert!(output.exists());
+
+        for file in [config.output.clone(), persistence::edits_file(&config), persistence::lock_file(&config)] {
+            std::fs::remove_file(file).unwrap();
+        }
+    }
+    #[test]
+    fn test_plan_merge_whole_source() {
+        //A working file started from the first rows of the download gets every later date of the download
+        let config = logic::Config::default();
+        let (_, data) = persistence::load_data(&config).unwrap();
+        assert_eq!(data.len(), persistence::FIRST_IMPORT_ROWS);
+        let (_, source) = persistence::load_all_data(&config).unwrap();
+        assert!(source.len() > data.len());
+
+        let plan = logic::plan_merge(&config, &data, &BTreeSet::new()).unwrap();
+        assert_eq!((plan.added.len(), plan.unchanged), (source.len() - data.len(), data.len()));
+        assert_eq!(plan.added.first(), source.get(persistence::FIRST_IMPORT_ROWS));
+        assert_eq!(plan.added.last().map(|r| r.date.as_str()), Some("2021-01-09"));
+    }
+    #[test]
+    fn test_diff_whole_files() {
+        let output = std::env::temp_dir().join(format!("tabledb-diff-{}.csv", std::process::id()));
+        let config = logic::Config {output: output.to_str().unwrap().to_string(), ..logic::Config::default()};
+        let (column_labels, data) = persistence::load_data(&config).unwrap();
+        persistence::save_data(&column_labels, &data, &config).unwrap();
+
+        //Rows of the download past the first import are compared too
+        let (_, changes) = logic::diff_files(&config, &config.output, &config.input).unwrap();
+        let total = persistence::load_all_data(&config).unwrap().1.len();
+        assert_eq!(crate::diff::counts(&changes), (total - data.len(), 0, 0));
+
+        //A copy of the download with a late row changed
+        let contents = std::fs::read_to_string(&config.input).unwrap();
+        let mut lines: Vec<String> = contents.lines().map(|l| l.to_string()).collect();
+        let mut fields: Vec<&str> = lines[150].split(',').collect();
+        fields[5] = "123456";
+        lines[150] = fields.join(",");
+        std::fs::write(&output, lines.join("\n")).unwrap();
+        let (_, changes) = logic::diff_files(&config, &config.input, &config.output).unwrap();
+        assert_eq!(crate::diff::counts(&changes), (0, 0, 1));
+        assert!(matches!(&changes[0], Change::Modified(old, new) if old.numconf != 123456 && new.numconf == 123456));
+        std::fs::remove_file(&output).unwrap();
+    }
 }
\ No newline at end of file
@@ -3,71 +3,151 @@
 #![allow(dead_code)]
 #![allow(unused_variables)]
 
-mod logic;
-mod datastore;
-mod persistence;
-
 use std::error::Error;
 use std::process;
 use std::env;
 use std::io;
 use std::fs;
+use std::collections::BTreeSet;
 
-use crate::logic::*;
-use crate::datastore::*;
+use table_db::logic::*;
+use table_db::datastore::{self, *};
+use table_db::index::Index;
+use table_db::messages::{self, Msg};
+use table_db::persistence;
+use table_db::rates::{Population, Rates};
+use table_db::server;
+use table_db::validate;
+use table_db::views::Views;
 
 fn main() -> Result<(), Box<dyn Error>> {
 
     //If new() function to create Config struct successful, return struct, else run code with error message
     let mut config = Config::new(env::args()).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {}", err);
+        eprintln!("{}", messages::fill(&Lang::EN, Msg::ErrArguments, &[&err]));
         process::exit(1);
     });
 
+    //Waiting for another session's lock is shown in the menu, and kept off the output of batch and serve modes
+    let waiting: fn(&Config) = if config.batch.is_some() || config.serve.is_some() {
+        |config| eprintln!("{}", config.fill(Msg::LockWaiting, &[&config.output]))
+    } else {
+        |config| println!("{}", config.fill(Msg::LockWaiting, &[&config.output]))
+    };
+    persistence::on_lock_wait(waiting);
+
     let mut column_labels: datastore::Header;
     let mut data: Vec<datastore::DataRow>;
+    let mut stamp: Option<persistence::Stamp>;
 
     //If load_data() function to create Vec<DataRow> from file successful, return vector, else run code with error message
-    match fs::File::open("datastore.csv") {
+    match fs::File::open(&config.output) {
         Ok(f) =>    {
-                        config.change_file(String::from("datastore.csv"));
-                        let both = load(&config).unwrap_or_else(|err| {
-                            eprintln!("Data\t error: {}", err);
+                        config.change_file(config.output.clone());
+                        let both = load_stamped(&config).unwrap_or_else(|err| {
+                            eprintln!("{}", config.fill(Msg::ErrData, &[&err]));
                             process::exit(1);
                         });
                         column_labels = both.0;
                         data = both.1;
+                        stamp = both.2;
                     }
         Err(e) =>   {
-                        let both = load(&config).unwrap_or_else(|err| {
-                            eprintln!("Data\t error: {}", err);
+                        let both = load_stamped(&config).unwrap_or_else(|err| {
+                            eprintln!("{}", config.fill(Msg::ErrData, &[&err]));
                             process::exit(1);
                         });
                         column_labels = both.0;
                         data = both.1;
+                        stamp = both.2;
                     }
     }
 
+    //Population table for per-capita rates: the user's population.csv if there is one, else the built-in table
+    let population = match fs::File::open("population.csv") {
+        Ok(f) => persistence::load_population("population.csv", &config).unwrap_or_else(|err| {
+            eprintln!("{}", config.fill(Msg::ErrPopulation, &[&err]));
+            process::exit(1);
+        }),
+        Err(e) => Population::default(),
+    };
+
+    //Saved column views, and the view to start with, from the settings file if there is one
+    let mut views = match fs::File::open(persistence::SETTINGS_FILE) {
+        Ok(f) => persistence::load_views(persistence::SETTINGS_FILE, &config).unwrap_or_else(|err| {
+            eprintln!("{}", config.fill(Msg::ErrData, &[&err]));
+            process::exit(1);
+        }),
+        Err(e) => Views::default(),
+    };
+
+    //Rows edited or deleted locally, which a merge leaves alone (only the working file has any)
+    let mut edited = if config.filename == config.output {
+        persistence::load_edits(&config).unwrap_or_else(|err| {
+            eprintln!("{}", config.fill(Msg::ErrData, &[&err]));
+            process::exit(1);
+        })
+    } else { BTreeSet::new() };
+
+    //Secondary indexes over the loaded rows, kept in step with every edit and delete below
+    let mut index = Index::new(&data);
+
+    //A batch edit file given on the command line is applied (or only shown, with --dry-run) and saved without starting the menu
+    if let Some(filename) = config.batch.clone() {
+        match run_batch(&filename, config.dry_run, &config, &population, &mut data, &mut index, &mut edited) {
+            Ok(changes) => print_changes(&filename, &changes, &config, &column_labels),
+            Err(e) => {
+                eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+                process::exit(1);
+            }
+        }
+        if config.dry_run {
+            println!("{}", config.text(Msg::BatchDryRun));
+        }
+        else if let Err(e) = save_merged(&column_labels, &mut data, &mut index, &mut edited, &config, &mut stamp) {
+            eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    //With --serve, the table is served over HTTP on localhost instead of starting the menu
+    if let Some(port) = config.serve {
+        let mut table = server::Table {config: &config, column_labels: &column_labels, population: &population,
+                                       data: &mut data, index: &mut index, edited: &mut edited, stamp: &mut stamp, persist: true};
+        if let Err(e) = server::serve(&format!("127.0.0.1:{}", port), &mut table) {
+            eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    //Check the loaded data for consistency problems, and give the user a chance to read them before the menu clears the screen
+    let problems = validate::validate(&data).len();
+    if problems > 0 {
+        println!("{}", config.fill(Msg::LoadProblems, &[&problems, &config.filename]));
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Failed to read line");
+    }
+
     loop {
         //Line below clears console window
-        std::process::Command::new("cmd").args(&["/C","cls"]).status().expect("Failed to execute process (CLS)");
+        std::process::Command::new("cmd").args(["/C","cls"]).status().expect("Failed to execute process (CLS)");
 
         //Print header lines, including column labels
-        println!("Covid Data CLI App - Thomas Ivanov");
+        println!("{}", config.text(Msg::Title));
         
         //Process user input to determine whether to show next page or quit
-        println!("\nInput a key to select an option (Q to exit)
-1) View all the current data
-2) Save current data to file
-3) View specific records
-4) Edit a record
-5) Delete a record
-6) Clear and refresh all records");
+        println!();
+        for msg in [Msg::MenuPrompt, Msg::MenuViewAll, Msg::MenuSave, Msg::MenuSearch, Msg::MenuEdit, Msg::MenuDelete, Msg::MenuRefresh,
+                    Msg::MenuSeries, Msg::MenuChart, Msg::MenuSummary, Msg::MenuValidate, Msg::MenuMerge, Msg::MenuDiff, Msg::MenuBatch, Msg::MenuExportData, Msg::MenuLanguage] {
+            println!("{}", config.text(msg));
+        }
         let mut input = String::new();
         io::stdin().read_line(&mut input).expect("Failed to read line");
         input = input.trim().to_string();
         if input.len() != 1 {
-            println!("String length is not 1! Press Enter to try again.");
+            println!("{}", config.text(Msg::MenuLength));
             io::stdin().read_line(&mut input).expect("Failed to read line");
             continue;
         }
@@ -77,15 +157,24 @@ fn main() -> Result<(), Box<dyn Error>> {
                 'q'|'Q' => break,
                 '1' => {
                     //Run function to load data, and if an error is output (propagated from function), run code with error message
-                    match display(&config, &column_labels, &data) {
-                        Err(e) => {eprintln!("Application\t error: {}", e);process::exit(1);}
-                        Ok(()) => {}
+                    if let Err(e) = display(&config, &column_labels, &data, &Rates::new(&data, &population), &mut views) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+                        process::exit(1);
                     }
                 },
                 '2' => {
+                    //Check the data before saving, and let the user back out if problems were found
+                    if display_violations(&config, &data) > 0 {
+                        println!("{}", config.text(Msg::SaveAnyway));
+                        let mut input = String::new();
+                        io::stdin().read_line(&mut input).expect("Failed to read line");
+                        if !messages::is_yes(&input) {
+                            continue;
+                        }
+                    }
                     //Run function to save data, and if an error is output (propagated from function), run code with error message
-                    if let Err(e) = save(&column_labels, &data) {
-                        eprintln!("Application\t error: {}", e);
+                    if let Err(e) = save_checked(&mut config, &mut column_labels, &mut data, &mut index, &mut edited, &mut stamp) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
                         process::exit(1);
                     }
                 },
@@ -93,12 +182,21 @@ fn main() -> Result<(), Box<dyn Error>> {
                     let mut input = String::new();
                     io::stdin().read_line(&mut input).expect("Failed to read line");
 
+                    //The name of a query saved in the settings file stands for its conditions
+                    if let Some(saved) = config.query(&input) {
+                        input = saved.clone();
+                    }
+
                     //The data row indices we collected in this list are used to collect the matching data rows
                     let mut out: Vec<DataRow> = Vec::new();
 
+                    //Inputs with conditions (e.g. "pruid=35") are looked up in the indexes, other inputs are row numbers
+                    let rates = Rates::new(&data, &population);
+                    let found = if input.contains(['=', '<', '>']) { query(input, &config, &data, &index, &rates) } else { search(input, &config, &data) };
+
                     //Run function to search data, and if an error is output (propagated from function), run code with error message
-                    match search(input, &config, &data) {
-                        Err(e) => {eprintln!("Application\t error: {}", e);process::exit(1);}
+                    match found {
+                        Err(e) => {eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));process::exit(1);}
                         Ok(final_data) => {
                                                         for entry in final_data {
                                                             if entry < data.len() {
@@ -107,7 +205,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                                                         }
                                                     
                                                         //The collected data rows are passed to our display function for the user to see
-                                                        display(&config, &column_labels, &out)?;
+                                                        display(&config, &column_labels, &out, &rates, &mut views)?;
                                                     }
                     }
                 },
@@ -115,8 +213,8 @@ fn main() -> Result<(), Box<dyn Error>> {
                     let mut input = String::new();
                     io::stdin().read_line(&mut input).expect("Failed to read line");
                     //Run function to edit data, and if an error is output (propagated from function), run code with error message
-                    if let Err(e) = edit(input, &config, &column_labels, &mut data) {
-                        eprintln!("Application\t error: {}", e);
+                    if let Err(e) = edit(input, &config, &column_labels, &mut data, &mut index, &mut edited) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
                         process::exit(1);
                     }
                 },
@@ -124,24 +222,89 @@ fn main() -> Result<(), Box<dyn Error>> {
                     let mut input = String::new();
                     io::stdin().read_line(&mut input).expect("Failed to read line");
                     //Run function to delete data, and if an error is output (propagated from function), run code with error message
-                    if let Err(e) = delete(input, &config, &column_labels, &mut data) {
-                        eprintln!("Application\t error: {}", e);
+                    if let Err(e) = delete(input, &config, &column_labels, &mut data, &mut index, &mut edited) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
                         process::exit(1);
                     }
                 },
                 '6' => {
                     //Run function to clear and refresh all data, and if an error is output (propagated from function), run code with error message
                     let both = refresh(&mut config).unwrap_or_else(|err| {
-                        eprintln!("Problem parsing arguments: {}", err);
+                        eprintln!("{}", config.fill(Msg::ErrData, &[&err]));
                         process::exit(1);
                     });
                     column_labels = both.0;
                     data = both.1;
+                    index = Index::new(&data);
+                    edited.clear();
+                },
+                'm'|'M' => {
+                    //Run function to merge a newer source download into the working data, and if an error is output, run code with error message
+                    if let Err(e) = merge(&config, &column_labels, &mut data, &mut index, &edited) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+                        process::exit(1);
+                    }
+                },
+                '7' => {
+                    let mut input = String::new();
+                    io::stdin().read_line(&mut input).expect("Failed to read line");
+                    //Run function to show the time series of a province (by pruid or name), and if an error is output, run code with error message
+                    if let Err(e) = display_series(input, &config, &column_labels, &data, &index) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+                        process::exit(1);
+                    }
+                },
+                '8' => {
+                    //Run function to chart a column for some provinces, and if an error is output, run code with error message
+                    if let Err(e) = display_chart(&config, &data, &index) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+                        process::exit(1);
+                    }
+                },
+                '9' => {
+                    //Run function to show the per-province summary, and if an error is output, run code with error message
+                    if let Err(e) = display_summary(&config, &data, &index) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+                        process::exit(1);
+                    }
+                },
+                'v'|'V' => {
+                    //Run function to list consistency problems, then wait so the user can read them
+                    display_violations(&config, &data);
+                    println!("\n{}", config.text(Msg::PressEnterBack));
+                    let mut input = String::new();
+                    io::stdin().read_line(&mut input).expect("Failed to read line");
+                },
+                'd'|'D' => {
+                    //Run function to compare two data files, and if an error is output, run code with error message
+                    if let Err(e) = display_diff(&config) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+                        process::exit(1);
+                    }
+                },
+                'b'|'B' => {
+                    //Run function to apply a batch edit file, and if an error is output, run code with error message
+                    if let Err(e) = batch_edit(&config, &column_labels, &population, &mut data, &mut index, &mut edited) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+                        process::exit(1);
+                    }
+                },
+                'x'|'X' => {
+                    //Run function to export the working data as an SQL script or Arrow file, and if an error is output, run code with error message
+                    if let Err(e) = export_data(&config, &data) {
+                        eprintln!("{}", config.fill(Msg::ErrApplication, &[&e]));
+                        process::exit(1);
+                    }
+                },
+                'l'|'L' => {
+                    //Switch the language of every message, label and province name for the rest of the session
+                    let lang = match config.language {Lang::EN => Lang::FR, Lang::FR => Lang::EN};
+                    config.change_lang(lang);
                 },
-                _ => {println!("Please enter a valid selection (1-5, Q)");continue}
+                _ => {println!("{}", config.text(Msg::MenuInvalid));continue}
             }
         }
     }
-    std::process::Command::new("cmd").args(&["/C","cls"]).status().expect("Failed to execute process (CLS)");
+    std::process::Command::new("cmd").args(["/C","cls"]).status().expect("Failed to execute process (CLS)");
     Ok(())
 }
\ No newline at end of file
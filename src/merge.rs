@@ -0,0 +1,113 @@
+//Final Project - Thomas Ivanov
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::datastore::DataRow;
+
+/// The key of a data row for matching rows between datasets: its `pruid` and `date`.
+pub type Key = (isize, String);
+
+/// Returns the key of a data row.
+pub fn key(row: &DataRow) -> Key {
+    (row.pruid, row.date.clone())
+}
+
+/// What merging a newer source download into the working data would do, row by row.
+///
+/// `changed` and `conflicts` refer to working rows by their index in the data vector. A conflict is a row
+/// that was edited (or deleted, with no index) locally and is different in the source, so the local
+/// version is kept.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergePlan {
+    pub added: Vec<DataRow>,
+    pub changed: Vec<(usize, DataRow)>,
+    pub conflicts: Vec<(Option<usize>, DataRow)>,
+    pub unchanged: usize,
+}
+
+/// Counts of what a merge did, for the summary report.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MergeReport {
+    pub added: usize,
+    pub accepted: usize,
+    pub rejected: usize,
+    pub conflicts: usize,
+    pub unchanged: usize,
+}
+
+/// Compares the working rows with the rows of a newer source download, matching them by `pruid` and `date`.
+///
+/// Source rows with a new key are added, and rows that differ are changes to accept or reject, unless the
+/// key is in `edited` (the rows edited or deleted locally), in which case they are conflicts. When a key
+/// appears more than once, only its first row is used.
+///
+/// # Examples
+///
+/// ```
+/// let plan = plan(&data, &source, &edited);
+/// println!("{} new rows", plan.added.len());
+/// ```
+pub fn plan(working: &[DataRow], source: &[DataRow], edited: &BTreeSet<Key>) -> MergePlan {
+    let mut positions: HashMap<Key, usize> = HashMap::new();
+    for (i, row) in working.iter().enumerate() {
+        positions.entry(key(row)).or_insert(i);
+    }
+
+    let mut plan = MergePlan::default();
+    let mut seen: BTreeSet<Key> = BTreeSet::new();
+    for row in source.iter() {
+        let k = key(row);
+        if !seen.insert(k.clone()) {
+            continue;
+        }
+        match positions.get(&k) {
+            Some(&i) if working[i] == *row => plan.unchanged += 1,
+            Some(&i) if edited.contains(&k) => plan.conflicts.push((Some(i), row.clone())),
+            Some(&i) => plan.changed.push((i, row.clone())),
+            None if edited.contains(&k) => plan.conflicts.push((None, row.clone())),
+            None => plan.added.push(row.clone()),
+        }
+    }
+    plan
+}
+
+/// Lists the fields that differ between two versions of a row, as `[label, old value, new value]` cells.
+/// `labels` are the labels of every stored column, in the order of `DataRow::public_vec`.
+pub fn changed_fields(labels: &[String], old: &DataRow, new: &DataRow) -> Vec<Vec<String>> {
+    labels.iter()
+            .zip(old.public_vec().into_iter().zip(new.public_vec()))
+            .filter(|(_, (o, n))| o != n)
+            .map(|(label, (o, n))| vec![label.clone(), o, n])
+            .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use crate::datastore::DataRow;
+    use crate::merge::{self, Key};
+
+    #[test]
+    fn test_merge_plan() {
+        let row = |pruid: &str, date: &str, total: &str| DataRow::new(vec![pruid, "Ontario", "Ontario", date, total, "0", "0", total, "0", "0.0"]);
+        let working = vec![row("35", "2020-03-01", "10"), row("35", "2020-03-02", "12"), row("35", "2020-03-03", "99")];
+        let source = vec![row("35", "2020-03-01", "10"), row("35", "2020-03-02", "15"), row("35", "2020-03-03", "20"),
+                          row("35", "2020-03-04", "25"), row("35", "2020-03-05", "30"), row("35", "2020-03-04", "0")];
+        //2020-03-03 was edited locally and 2020-03-05 was deleted locally
+        let edited: BTreeSet<Key> = vec![(35, String::from("2020-03-03")), (35, String::from("2020-03-05"))].into_iter().collect();
+
+        let plan = merge::plan(&working, &source, &edited);
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.changed, vec![(1, source[1].clone())]);
+        assert_eq!(plan.conflicts, vec![(Some(2), source[2].clone()), (None, source[4].clone())]);
+        assert_eq!(plan.added, vec![source[3].clone()]);
+
+        let labels: Vec<String> = ["pruid", "prname", "prnameFR", "date", "numconf", "numprob", "numdeaths", "numtotal", "numtoday", "ratetotal"]
+                                    .iter().map(|l| l.to_string()).collect();
+        assert_eq!(merge::changed_fields(&labels, &working[1], &source[1]), vec![
+            vec![String::from("numconf"), String::from("12"), String::from("15")],
+            vec![String::from("numtotal"), String::from("12"), String::from("15")],
+        ]);
+    }
+}
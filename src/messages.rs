@@ -0,0 +1,562 @@
+//Final Project - Thomas Ivanov
+
+use std::fmt::Display;
+
+use crate::logic::Lang;
+
+/// Keys of the message catalog, one for every user-facing string.
+///
+/// Messages with `{}` placeholders are filled in order with `fill`. Each key must have a translation in
+/// both `en` and `fr` (the `match` statements won't compile otherwise), and both translations must have
+/// the same number of placeholders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Msg {
+    Title,
+    MenuPrompt,
+    MenuViewAll,
+    MenuSave,
+    MenuSearch,
+    MenuEdit,
+    MenuDelete,
+    MenuRefresh,
+    MenuSeries,
+    MenuChart,
+    MenuSummary,
+    MenuValidate,
+    MenuMerge,
+    MenuDiff,
+    MenuBatch,
+    MenuExportData,
+    MenuLanguage,
+    MenuLength,
+    MenuInvalid,
+    ErrArguments,
+    ErrData,
+    ErrPopulation,
+    ErrApplication,
+    ArgLanguageInvalid,
+    ArgLanguageMissing,
+    ArgUnknown,
+    ArgValueMissing,
+    ArgValueInvalid,
+    ArgDryRunWithoutBatch,
+    ConfigSameFiles,
+    LoadProblems,
+    SaveAnyway,
+    PressEnterBack,
+    PageStatus,
+    PageHelp,
+    DisplayHelp,
+    SortPrompt,
+    SortInvalid,
+    ColumnsPrompt,
+    ViewSavePrompt,
+    ViewUnknownColumn,
+    ViewNameInvalid,
+    SettingsLineInvalid,
+    SettingsViewInvalid,
+    SettingsViewMissing,
+    SettingsUnknown,
+    SettingsValueInvalid,
+    EditsLineInvalid,
+    DiffColumn,
+    DiffOld,
+    DiffNew,
+    MergeChanged,
+    MergeAccept,
+    MergeConflict,
+    MergeConflictDeleted,
+    MergeSummary,
+    DiffChange,
+    DiffAdded,
+    DiffRemoved,
+    DiffModified,
+    DiffFilesPrompt,
+    DiffFilesInvalid,
+    DiffSummary,
+    DiffExportPrompt,
+    DiffExported,
+    ExportPrompt,
+    ExportFormatUnknown,
+    Exported,
+    ExportDataPrompt,
+    SqlExported,
+    SqlSyntax,
+    SqlColumnUnknown,
+    SqlValueCount,
+    LoadFileInvalid,
+    ArrowColumnMissing,
+    ArrowColumnType,
+    ExportDataFormatUnknown,
+    BatchFilePrompt,
+    BatchCommandUnknown,
+    BatchSyntax,
+    BatchRowMissing,
+    BatchRowExists,
+    BatchLineError,
+    BatchSummary,
+    BatchDryRun,
+    BatchApplyPrompt,
+    BatchApplied,
+    ServeListening,
+    ServeNotFound,
+    ServeRowNotFound,
+    ServeRowExists,
+    ServeBodyInvalid,
+    ServeParamInvalid,
+    ArgServeWithBatch,
+    ChartColumnPrompt,
+    ChartProvincesPrompt,
+    ChartKindPrompt,
+    ChartTitle,
+    ChartNotNumeric,
+    ChartNoProvinces,
+    SummaryRows,
+    ProvinceNotFound,
+    ProblemsFound,
+    SearchInvalid,
+    QueryInvalid,
+    QueryUnknownAbbr,
+    QueryBadColumn,
+    QueryNotNumber,
+    QueryNotNumericColumn,
+    EditPrompt,
+    EditPruidPrompt,
+    EditNamePrompt,
+    EditDatePrompt,
+    EditCountPrompt,
+    EditRatePrompt,
+    EditColumnInvalid,
+    ColumnUnknown,
+    SchemaMissing,
+    SchemaNotInteger,
+    SchemaNotNumber,
+    SchemaNotDate,
+    SchemaTooSmall,
+    SchemaTooLarge,
+    SchemaTooLong,
+    SchemaPrecision,
+    SchemaPattern,
+    SchemaNotAllowed,
+    LoadRowInvalid,
+    LoadRowColumns,
+    SaveRowInvalid,
+    LockWaiting,
+    SaveConflict,
+    SaveConflictPrompt,
+    SaveReloaded,
+    SaveCancelled,
+    EditRowInvalid,
+    EditKeepGoing,
+    DeletePrompt,
+    FileEmpty,
+    FileFormatUnknown,
+    PopulationLineInvalid,
+    SeriesDateInvalid,
+    RuleTotalSum,
+    RuleProvinceName,
+    RuleDailyChange,
+    RuleCumulative,
+    ViolationRow,
+}
+
+/// Every key of the catalog, for checking translations.
+pub const ALL: [Msg; 147] = [
+    Msg::Title, Msg::MenuPrompt, Msg::MenuViewAll, Msg::MenuSave, Msg::MenuSearch, Msg::MenuEdit, Msg::MenuDelete,
+    Msg::MenuRefresh, Msg::MenuSeries, Msg::MenuChart, Msg::MenuSummary, Msg::MenuValidate, Msg::MenuMerge, Msg::MenuDiff, Msg::MenuBatch, Msg::MenuExportData,
+    Msg::MenuLanguage,
+    Msg::MenuLength, Msg::MenuInvalid, Msg::ErrArguments, Msg::ErrData, Msg::ErrPopulation, Msg::ErrApplication,
+    Msg::ArgLanguageInvalid, Msg::ArgLanguageMissing, Msg::LoadProblems, Msg::SaveAnyway, Msg::PressEnterBack,
+    Msg::PageStatus, Msg::PageHelp, Msg::DisplayHelp, Msg::SortPrompt, Msg::SortInvalid, Msg::ColumnsPrompt, Msg::ViewSavePrompt,
+    Msg::ViewUnknownColumn, Msg::ViewNameInvalid, Msg::SettingsLineInvalid, Msg::SettingsViewInvalid, Msg::SettingsViewMissing, Msg::SettingsUnknown,
+    Msg::SettingsValueInvalid, Msg::ArgUnknown, Msg::ArgValueMissing, Msg::ArgValueInvalid, Msg::ArgDryRunWithoutBatch, Msg::ConfigSameFiles, Msg::EditsLineInvalid,
+    Msg::DiffColumn, Msg::DiffOld, Msg::DiffNew, Msg::MergeChanged, Msg::MergeAccept, Msg::MergeConflict, Msg::MergeConflictDeleted,
+    Msg::MergeSummary, Msg::DiffChange, Msg::DiffAdded, Msg::DiffRemoved, Msg::DiffModified, Msg::DiffFilesPrompt, Msg::DiffFilesInvalid,
+    Msg::DiffSummary, Msg::DiffExportPrompt, Msg::DiffExported, Msg::BatchFilePrompt, Msg::BatchCommandUnknown, Msg::BatchSyntax,
+    Msg::BatchRowMissing, Msg::BatchRowExists, Msg::BatchLineError, Msg::BatchSummary, Msg::BatchDryRun, Msg::BatchApplyPrompt,
+    Msg::BatchApplied, Msg::ServeListening, Msg::ServeNotFound, Msg::ServeRowNotFound, Msg::ServeRowExists,
+    Msg::ServeBodyInvalid, Msg::ServeParamInvalid, Msg::ArgServeWithBatch, Msg::FileFormatUnknown, Msg::ChartColumnPrompt,
+    Msg::ChartProvincesPrompt, Msg::ChartKindPrompt, Msg::ChartTitle, Msg::ChartNotNumeric, Msg::ChartNoProvinces,
+    Msg::SummaryRows, Msg::ProvinceNotFound, Msg::ProblemsFound, Msg::SearchInvalid, Msg::QueryInvalid,
+    Msg::QueryUnknownAbbr, Msg::QueryBadColumn, Msg::QueryNotNumber, Msg::QueryNotNumericColumn, Msg::EditPrompt,
+    Msg::EditPruidPrompt, Msg::EditNamePrompt, Msg::EditDatePrompt, Msg::EditCountPrompt, Msg::EditRatePrompt, Msg::EditColumnInvalid, Msg::ColumnUnknown,
+    Msg::SchemaMissing, Msg::SchemaNotInteger, Msg::SchemaNotNumber, Msg::SchemaNotDate, Msg::SchemaTooSmall, Msg::SchemaTooLarge, Msg::SchemaTooLong,
+    Msg::SchemaPrecision, Msg::SchemaPattern, Msg::SchemaNotAllowed, Msg::LoadRowInvalid, Msg::LoadRowColumns, Msg::SaveRowInvalid, Msg::EditRowInvalid,
+    Msg::LockWaiting, Msg::SaveConflict, Msg::SaveConflictPrompt, Msg::SaveReloaded, Msg::SaveCancelled, Msg::ExportPrompt, Msg::ExportFormatUnknown, Msg::Exported,
+    Msg::ExportDataPrompt, Msg::SqlExported, Msg::SqlSyntax, Msg::SqlColumnUnknown, Msg::SqlValueCount, Msg::LoadFileInvalid,
+    Msg::ArrowColumnMissing, Msg::ArrowColumnType, Msg::ExportDataFormatUnknown,
+    Msg::EditKeepGoing, Msg::DeletePrompt, Msg::FileEmpty, Msg::PopulationLineInvalid,
+    Msg::SeriesDateInvalid, Msg::RuleTotalSum, Msg::RuleProvinceName, Msg::RuleDailyChange, Msg::RuleCumulative,
+    Msg::ViolationRow,
+];
+
+/// Returns the text of a message in a language.
+pub fn text(lang: &Lang, msg: Msg) -> &'static str {
+    match lang {
+        Lang::EN => en(msg),
+        Lang::FR => fr(msg),
+    }
+}
+
+/// Returns the text of a message in a language, with each `{}` placeholder replaced by the next argument.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(fill(&Lang::FR, Msg::ChartTitle, &[&"numtoday"]), "numtoday par date");
+/// ```
+pub fn fill(lang: &Lang, msg: Msg, args: &[&dyn Display]) -> String {
+    let mut parts = text(lang, msg).split("{}");
+    let mut out = String::from(parts.next().unwrap_or(""));
+    for (i, part) in parts.enumerate() {
+        if let Some(arg) = args.get(i) {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+/// Returns a message in both languages ("English / French"), for errors found before the language is known.
+pub fn both(msg: Msg, args: &[&dyn Display]) -> String {
+    format!("{} / {}", fill(&Lang::EN, msg, args), fill(&Lang::FR, msg, args))
+}
+
+/// Returns true if the answer to a yes/no question is yes, in either language ("y" or "o" for "oui").
+pub fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes" | "o" | "oui")
+}
+
+fn en(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Title => "Covid Data CLI App - Thomas Ivanov",
+        Msg::MenuPrompt => "Input a key to select an option (Q to exit)",
+        Msg::MenuViewAll => "1) View all the current data",
+        Msg::MenuSave => "2) Save current data to file",
+        Msg::MenuSearch => "3) View specific records",
+        Msg::MenuEdit => "4) Edit a record",
+        Msg::MenuDelete => "5) Delete a record",
+        Msg::MenuRefresh => "6) Clear and refresh all records",
+        Msg::MenuSeries => "7) View the time series of a province",
+        Msg::MenuChart => "8) Chart a column over time",
+        Msg::MenuSummary => "9) View a summary of each province",
+        Msg::MenuValidate => "V) Check the data for consistency problems",
+        Msg::MenuMerge => "M) Merge a newer download of the source file",
+        Msg::MenuDiff => "D) Compare two data files",
+        Msg::MenuBatch => "B) Apply a batch edit file",
+        Msg::MenuExportData => "X) Export the working data as an SQL script or an Arrow file",
+        Msg::MenuLanguage => "L) Passer au français",
+        Msg::MenuLength => "String length is not 1! Press Enter to try again.",
+        Msg::MenuInvalid => "Please enter a valid selection (1-9, V, M, D, B, X, L, Q)",
+        Msg::ErrArguments => "Problem parsing arguments: {}",
+        Msg::ErrData => "Data\t error: {}",
+        Msg::ErrPopulation => "Population\t error: {}",
+        Msg::ErrApplication => "Application\t error: {}",
+        Msg::ArgLanguageInvalid => "Must supply a valid language (en/fr)",
+        Msg::ArgLanguageMissing => "Must supply a language option (en/fr)",
+        Msg::ArgUnknown => "Unknown option \"{}\" (the options are {})",
+        Msg::ArgValueMissing => "Option {} must be followed by a value",
+        Msg::ArgValueInvalid => "\"{}\" is not a valid value for option {}",
+        Msg::ArgDryRunWithoutBatch => "Option --dry-run needs a batch edit file (--batch FILE)",
+        Msg::ConfigSameFiles => "The input and output files must be different (both are {})",
+        Msg::LoadProblems => "Warning: {} consistency problem(s) found in {} (select V to list them). Press Enter to continue.",
+        Msg::SaveAnyway => "Save anyway? y/N",
+        Msg::PressEnterBack => "[Press ENTER to go back]",
+        Msg::PageStatus => "Page {} of {}, rows {}-{} of {}",
+        Msg::PageHelp => "[Press ENTER or N for the next page, P for the previous page, F/L for the first/last page, G and a number to go to a page, Z and a number to set the rows per page, or Q to go back]",
+        Msg::DisplayHelp => "[Press S to sort output by column, R to reverse output (asc-desc), C to choose the columns shown, or E to export them]",
+        Msg::SortPrompt => "Choose one or more columns (1-13, separated by commas) to sort for, in order of priority: ",
+        Msg::SortInvalid => "Please select a valid number (1-13)",
+        Msg::ColumnsPrompt => "Choose the columns to show, in order and separated by commas ({}), * for all of them, or a saved view ({}): ",
+        Msg::ViewSavePrompt => "Enter a name to save these columns as a view, or press ENTER to skip: ",
+        Msg::ViewUnknownColumn => "Unknown column \"{}\" (a view must show at least one column)",
+        Msg::ViewNameInvalid => "Invalid view name \"{}\" (use letters, digits, - and _, and not a column name)",
+        Msg::SettingsLineInvalid => "Settings file {} invalid: line {} is not a [section] or a setting (name = value)",
+        Msg::SettingsViewInvalid => "Settings file {} invalid: view \"{}\" has an unknown column \"{}\"",
+        Msg::SettingsViewMissing => "Settings file {} invalid: there is no saved view named \"{}\"",
+        Msg::SettingsUnknown => "Settings file {} invalid: unknown setting \"{}\" in [{}]",
+        Msg::SettingsValueInvalid => "Settings file {} invalid: \"{}\" is not a valid value for {} in [{}]",
+        Msg::EditsLineInvalid => "Edits file {} invalid: line {} must be pruid,date",
+        Msg::DiffColumn => "column",
+        Msg::DiffOld => "old",
+        Msg::DiffNew => "new",
+        Msg::MergeChanged => "Row {} ({} {}) has changed in {}:",
+        Msg::MergeAccept => "Accept this change? y/N (A to accept this and all the remaining changes)",
+        Msg::MergeConflict => "Row {} ({} {}) was edited locally and has changed in {}, so the local version is kept:",
+        Msg::MergeConflictDeleted => "Row {} {} was deleted locally and is in {}, so it stays deleted",
+        Msg::MergeSummary => "Merge of {}: {} row(s) added, {} change(s) accepted, {} change(s) rejected, {} conflict(s) with local edits, {} row(s) unchanged",
+        Msg::DiffChange => "change",
+        Msg::DiffAdded => "added",
+        Msg::DiffRemoved => "removed",
+        Msg::DiffModified => "modified",
+        Msg::DiffFilesPrompt => "Files to compare, old then new, separated by a comma (press ENTER for {} and {}): ",
+        Msg::DiffFilesInvalid => "Please enter two file names separated by a comma",
+        Msg::DiffSummary => "{} to {}: {} row(s) added, {} removed, {} modified",
+        Msg::DiffExportPrompt => "Enter a file name to export this diff as CSV, or press ENTER to skip: ",
+        Msg::DiffExported => "Diff saved to {}",
+        Msg::ExportPrompt => "Enter a file name ending in .md, .html or .tex to export the {} rows shown, or press ENTER to skip: ",
+        Msg::ExportFormatUnknown => "Cannot export to {}: the file name must end in .md (Markdown), .html (HTML) or .tex (LaTeX)",
+        Msg::Exported => "{} rows exported to {}",
+        Msg::ExportDataPrompt => "Enter a file name ending in .sql (SQL script) or .arrow (Arrow IPC), or press ENTER for {}: ",
+        Msg::SqlExported => "{} rows exported to {} as table {}",
+        Msg::SqlSyntax => "Line {}: invalid SQL near \"{}\"",
+        Msg::SqlColumnUnknown => "Line {}: there is no column {}",
+        Msg::SqlValueCount => "Line {}: {} values for {} columns",
+        Msg::LoadFileInvalid => "File {}: {}",
+        Msg::ArrowColumnMissing => "The Arrow file has no column {}",
+        Msg::ArrowColumnType => "Column {} of the Arrow file has type {}, which cannot be read",
+        Msg::ExportDataFormatUnknown => "Cannot export to {}: the file name must end in .sql (SQL script) or .arrow (Arrow IPC)",
+        Msg::BatchFilePrompt => "Enter the name of the batch edit file (press ENTER to go back): ",
+        Msg::BatchCommandUnknown => "Line {}: unknown command \"{}\" (must be set, delete or insert)",
+        Msg::BatchSyntax => "Line {}: expected {}",
+        Msg::BatchRowMissing => "Line {}: there is no row for {} {}",
+        Msg::BatchRowExists => "Line {}: there is already a row for {} {}",
+        Msg::BatchLineError => "Line {}: {}",
+        Msg::BatchSummary => "{}: {} row(s) added, {} removed, {} modified",
+        Msg::BatchDryRun => "Dry run: nothing was changed or saved.",
+        Msg::BatchApplyPrompt => "Apply these changes? y/N",
+        Msg::BatchApplied => "The changes were applied. Save (option 2) to keep them.",
+        Msg::ServeListening => "Serving {} rows on http://{} (press Ctrl+C to stop)",
+        Msg::ServeNotFound => "No endpoint for {} {}",
+        Msg::ServeRowNotFound => "There is no row {}",
+        Msg::ServeRowExists => "There is already a row for {} {}",
+        Msg::ServeBodyInvalid => "The request body must be a JSON object of column values",
+        Msg::ServeParamInvalid => "Invalid value \"{}\" for parameter {}",
+        Msg::ArgServeWithBatch => "Options --serve and --batch cannot be used together",
+        Msg::ChartColumnPrompt => "Column to chart (numconf, numprob, numdeaths, numtotal, numtoday, ratetotal): ",
+        Msg::ChartProvincesPrompt => "Provinces to chart (pruids, abbreviations or names, separated by commas): ",
+        Msg::ChartKindPrompt => "Chart type (L for line, B for bar): ",
+        Msg::ChartTitle => "{} by date",
+        Msg::ChartNotNumeric => "Column \"{}\" is not a numeric column",
+        Msg::ChartNoProvinces => "Must supply at least one province to chart",
+        Msg::SummaryRows => "rows",
+        Msg::ProvinceNotFound => "No rows found for province \"{}\"",
+        Msg::ProblemsFound => "{} problem(s) found in {} rows",
+        Msg::SearchInvalid => "Search string parameter invalid. Please refer to the following example for formatting (without quotes): \"4, 7, 9-14\"",
+        Msg::QueryInvalid => "Query condition invalid. Please refer to the following example for formatting (without quotes): \"pruid=35, date=2020-03-01..2020-03-31, numtoday>100\"",
+        Msg::QueryUnknownAbbr => "Unknown province abbreviation \"{}\"",
+        Msg::QueryBadColumn => "Cannot query by column \"{}\" (use pruid, prname, abbr or date)",
+        Msg::QueryNotNumber => "Cannot compare column \"{}\" with \"{}\" (must be a number)",
+        Msg::QueryNotNumericColumn => "Cannot compare column \"{}\" (must be a numeric column)",
+        Msg::EditPrompt => "Choose a column (1-10) to edit from the following (row {}). Enter Q to quit.",
+        Msg::EditPruidPrompt => "Choose a new value for this line (max 3 digits)",
+        Msg::EditNamePrompt => "Choose a new value for this line (max 30 characters)",
+        Msg::EditDatePrompt => "Choose a new value for this line (max 15 characters)",
+        Msg::EditCountPrompt => "Choose a new value for this line (max 8 digits)",
+        Msg::EditRatePrompt => "Choose a new value for this line (max 99999.99, no more than two digits after decimal point)",
+        Msg::EditColumnInvalid => "Please select a valid number (1-10)",
+        Msg::ColumnUnknown => "There is no column \"{}\" (the columns are {}, or 1-10)",
+        Msg::SchemaMissing => "{}: a value is required",
+        Msg::SchemaNotInteger => "{}: \"{}\" is not a whole number",
+        Msg::SchemaNotNumber => "{}: \"{}\" is not a number",
+        Msg::SchemaNotDate => "{}: \"{}\" is not a valid YYYY-MM-DD date",
+        Msg::SchemaTooSmall => "{}: {} is less than the minimum of {}",
+        Msg::SchemaTooLarge => "{}: {} is more than the maximum of {}",
+        Msg::SchemaTooLong => "{}: \"{}\" is longer than {} characters",
+        Msg::SchemaPrecision => "{}: {} has more than {} digit(s) after the decimal point",
+        Msg::SchemaPattern => "{}: \"{}\" does not match the pattern {}",
+        Msg::SchemaNotAllowed => "{}: \"{}\" is not one of {}",
+        Msg::LoadRowInvalid => "File {}, line {}: {}",
+        Msg::LoadRowColumns => "File {}, line {}: expected {} values, found {}",
+        Msg::SaveRowInvalid => "Row {} cannot be saved: {}",
+        Msg::LockWaiting => "Waiting for another session to finish with {}...",
+        Msg::SaveConflict => "{} was saved by another session since it was loaded",
+        Msg::SaveConflictPrompt => "R to reload it (dropping the changes made here), M to merge its changes into yours, O to overwrite it, or ENTER to cancel: ",
+        Msg::SaveReloaded => "Reloaded {} rows from {}",
+        Msg::SaveCancelled => "Nothing was saved",
+        Msg::EditRowInvalid => "There is no row {} (the table has {} rows)",
+        Msg::EditKeepGoing => "Do you want to keep editing? Enter Q to quit (any key to continue)",
+        Msg::DeletePrompt => "Do you want to delete the following (row {})? y/N",
+        Msg::FileEmpty => "File contents invalid: File must have at least one line of text",
+        Msg::FileFormatUnknown => "File {} is neither a source download nor a saved data file",
+        Msg::PopulationLineInvalid => "Population file invalid: line {} must have 2 columns (pruid,population)",
+        Msg::SeriesDateInvalid => "Row {} has an invalid date \"{}\"",
+        Msg::RuleTotalSum => "numtotal {} is not numconf {} + numprob {}",
+        Msg::RuleProvinceName => "name \"{}\"/\"{}\" differs from \"{}\"/\"{}\" used by other rows of pruid {}",
+        Msg::RuleDailyChange => "numtoday {} does not match the change in numtotal ({} to {})",
+        Msg::RuleCumulative => "{} went down from {} on {} to {}",
+        Msg::ViolationRow => "Row {} ({} {}): {}",
+    }
+}
+
+fn fr(msg: Msg) -> &'static str {
+    match msg {
+        Msg::Title => "Application CLI de données Covid - Thomas Ivanov",
+        Msg::MenuPrompt => "Entrez une touche pour choisir une option (Q pour quitter)",
+        Msg::MenuViewAll => "1) Voir toutes les données actuelles",
+        Msg::MenuSave => "2) Enregistrer les données actuelles dans le fichier",
+        Msg::MenuSearch => "3) Voir des enregistrements précis",
+        Msg::MenuEdit => "4) Modifier un enregistrement",
+        Msg::MenuDelete => "5) Supprimer un enregistrement",
+        Msg::MenuRefresh => "6) Effacer et recharger tous les enregistrements",
+        Msg::MenuSeries => "7) Voir la série chronologique d'une province",
+        Msg::MenuChart => "8) Tracer une colonne dans le temps",
+        Msg::MenuSummary => "9) Voir un résumé de chaque province",
+        Msg::MenuValidate => "V) Vérifier la cohérence des données",
+        Msg::MenuMerge => "M) Fusionner un nouveau téléchargement du fichier source",
+        Msg::MenuDiff => "D) Comparer deux fichiers de données",
+        Msg::MenuBatch => "B) Appliquer un fichier de modifications par lots",
+        Msg::MenuExportData => "X) Exporter les données de travail en script SQL ou en fichier Arrow",
+        Msg::MenuLanguage => "L) Switch to English",
+        Msg::MenuLength => "La saisie doit faire 1 caractère! Appuyez sur Entrée pour réessayer.",
+        Msg::MenuInvalid => "Veuillez entrer un choix valide (1-9, V, M, D, B, X, L, Q)",
+        Msg::ErrArguments => "Problème d'analyse des arguments : {}",
+        Msg::ErrData => "Erreur\t de données : {}",
+        Msg::ErrPopulation => "Erreur\t de population : {}",
+        Msg::ErrApplication => "Erreur\t d'application : {}",
+        Msg::ArgLanguageInvalid => "Veuillez fournir une langue valide (en/fr)",
+        Msg::ArgLanguageMissing => "Veuillez fournir une option de langue (en/fr)",
+        Msg::ArgUnknown => "Option inconnue « {} » (les options sont {})",
+        Msg::ArgValueMissing => "L'option {} doit être suivie d'une valeur",
+        Msg::ArgValueInvalid => "« {} » n'est pas une valeur valide pour l'option {}",
+        Msg::ArgDryRunWithoutBatch => "L'option --dry-run nécessite un fichier de modifications par lots (--batch FICHIER)",
+        Msg::ConfigSameFiles => "Les fichiers d'entrée et de sortie doivent être différents (les deux sont {})",
+        Msg::LoadProblems => "Attention : {} problème(s) de cohérence trouvé(s) dans {} (choisissez V pour les afficher). Appuyez sur Entrée pour continuer.",
+        Msg::SaveAnyway => "Enregistrer quand même? o/N",
+        Msg::PressEnterBack => "[Appuyez sur ENTRÉE pour revenir]",
+        Msg::PageStatus => "Page {} de {}, lignes {}-{} sur {}",
+        Msg::PageHelp => "[Appuyez sur ENTRÉE ou N pour la page suivante, P pour la page précédente, F/L pour la première/dernière page, G et un numéro pour aller à une page, Z et un nombre pour choisir les lignes par page, ou Q pour revenir]",
+        Msg::DisplayHelp => "[Appuyez sur S pour trier par colonne, R pour inverser l'ordre (croissant-décroissant), C pour choisir les colonnes affichées, ou E pour les exporter]",
+        Msg::SortPrompt => "Choisissez une ou plusieurs colonnes (1-13, séparées par des virgules) pour le tri, par ordre de priorité : ",
+        Msg::SortInvalid => "Veuillez choisir un numéro valide (1-13)",
+        Msg::ColumnsPrompt => "Choisissez les colonnes à afficher, dans l'ordre et séparées par des virgules ({}), * pour toutes, ou une vue enregistrée ({}) : ",
+        Msg::ViewSavePrompt => "Entrez un nom pour enregistrer ces colonnes comme vue, ou appuyez sur ENTRÉE pour passer : ",
+        Msg::ViewUnknownColumn => "Colonne inconnue « {} » (une vue doit afficher au moins une colonne)",
+        Msg::ViewNameInvalid => "Nom de vue invalide « {} » (utilisez des lettres, des chiffres, - et _, et pas un nom de colonne)",
+        Msg::SettingsLineInvalid => "Fichier de paramètres {} invalide : la ligne {} n'est ni une [section] ni un paramètre (nom = valeur)",
+        Msg::SettingsViewInvalid => "Fichier de paramètres {} invalide : la vue « {} » a une colonne inconnue « {} »",
+        Msg::SettingsViewMissing => "Fichier de paramètres {} invalide : il n'y a pas de vue enregistrée nommée « {} »",
+        Msg::SettingsUnknown => "Fichier de paramètres {} invalide : paramètre inconnu « {} » dans [{}]",
+        Msg::SettingsValueInvalid => "Fichier de paramètres {} invalide : « {} » n'est pas une valeur valide pour {} dans [{}]",
+        Msg::EditsLineInvalid => "Fichier de modifications {} invalide : la ligne {} doit être pruid,date",
+        Msg::DiffColumn => "colonne",
+        Msg::DiffOld => "ancien",
+        Msg::DiffNew => "nouveau",
+        Msg::MergeChanged => "La ligne {} ({} {}) a changé dans {} :",
+        Msg::MergeAccept => "Accepter ce changement? o/N (A pour accepter celui-ci et tous les suivants)",
+        Msg::MergeConflict => "La ligne {} ({} {}) a été modifiée localement et a changé dans {}, la version locale est donc conservée :",
+        Msg::MergeConflictDeleted => "La ligne {} {} a été supprimée localement et se trouve dans {}, elle reste donc supprimée",
+        Msg::MergeSummary => "Fusion de {} : {} ligne(s) ajoutée(s), {} changement(s) accepté(s), {} changement(s) refusé(s), {} conflit(s) avec des modifications locales, {} ligne(s) inchangée(s)",
+        Msg::DiffChange => "changement",
+        Msg::DiffAdded => "ajoutée",
+        Msg::DiffRemoved => "supprimée",
+        Msg::DiffModified => "modifiée",
+        Msg::DiffFilesPrompt => "Fichiers à comparer, l'ancien puis le nouveau, séparés par une virgule (appuyez sur ENTRÉE pour {} et {}) : ",
+        Msg::DiffFilesInvalid => "Veuillez entrer deux noms de fichiers séparés par une virgule",
+        Msg::DiffSummary => "{} à {} : {} ligne(s) ajoutée(s), {} supprimée(s), {} modifiée(s)",
+        Msg::DiffExportPrompt => "Entrez un nom de fichier pour exporter cette comparaison en CSV, ou appuyez sur ENTRÉE pour passer : ",
+        Msg::DiffExported => "Comparaison enregistrée dans {}",
+        Msg::ExportPrompt => "Entrez un nom de fichier se terminant par .md, .html ou .tex pour exporter les {} lignes affichées, ou appuyez sur ENTRÉE pour passer : ",
+        Msg::ExportFormatUnknown => "Impossible d'exporter vers {} : le nom de fichier doit se terminer par .md (Markdown), .html (HTML) ou .tex (LaTeX)",
+        Msg::Exported => "{} lignes exportées vers {}",
+        Msg::ExportDataPrompt => "Entrez un nom de fichier se terminant par .sql (script SQL) ou .arrow (Arrow IPC), ou appuyez sur ENTRÉE pour {} : ",
+        Msg::SqlExported => "{} lignes exportées vers {} dans la table {}",
+        Msg::SqlSyntax => "Ligne {} : SQL invalide près de « {} »",
+        Msg::SqlColumnUnknown => "Ligne {} : il n'y a pas de colonne {}",
+        Msg::SqlValueCount => "Ligne {} : {} valeurs pour {} colonnes",
+        Msg::LoadFileInvalid => "Fichier {} : {}",
+        Msg::ArrowColumnMissing => "Le fichier Arrow n'a pas de colonne {}",
+        Msg::ArrowColumnType => "La colonne {} du fichier Arrow est de type {}, qui ne peut pas être lu",
+        Msg::ExportDataFormatUnknown => "Impossible d'exporter vers {} : le nom de fichier doit se terminer par .sql (script SQL) ou .arrow (Arrow IPC)",
+        Msg::BatchFilePrompt => "Entrez le nom du fichier de modifications par lots (appuyez sur ENTRÉE pour revenir) : ",
+        Msg::BatchCommandUnknown => "Ligne {} : commande « {} » inconnue (doit être set, delete ou insert)",
+        Msg::BatchSyntax => "Ligne {} : {} attendu",
+        Msg::BatchRowMissing => "Ligne {} : il n'y a pas de ligne pour {} {}",
+        Msg::BatchRowExists => "Ligne {} : il y a déjà une ligne pour {} {}",
+        Msg::BatchLineError => "Ligne {} : {}",
+        Msg::BatchSummary => "{} : {} ligne(s) ajoutée(s), {} supprimée(s), {} modifiée(s)",
+        Msg::BatchDryRun => "Essai : rien n'a été modifié ni enregistré.",
+        Msg::BatchApplyPrompt => "Appliquer ces modifications? o/N",
+        Msg::BatchApplied => "Les modifications ont été appliquées. Enregistrez (option 2) pour les conserver.",
+        Msg::ServeListening => "Service de {} lignes sur http://{} (appuyez sur Ctrl+C pour arrêter)",
+        Msg::ServeNotFound => "Aucun point d'accès pour {} {}",
+        Msg::ServeRowNotFound => "Il n'y a pas de ligne {}",
+        Msg::ServeRowExists => "Il y a déjà une ligne pour {} {}",
+        Msg::ServeBodyInvalid => "Le corps de la requête doit être un objet JSON de valeurs de colonnes",
+        Msg::ServeParamInvalid => "Valeur « {} » invalide pour le paramètre {}",
+        Msg::ArgServeWithBatch => "Les options --serve et --batch ne peuvent pas être utilisées ensemble",
+        Msg::ChartColumnPrompt => "Colonne à tracer (numconf, numprob, numdeaths, numtotal, numtoday, ratetotal) : ",
+        Msg::ChartProvincesPrompt => "Provinces à tracer (pruids, abréviations ou noms, séparés par des virgules) : ",
+        Msg::ChartKindPrompt => "Type de graphique (L pour lignes, B pour barres) : ",
+        Msg::ChartTitle => "{} par date",
+        Msg::ChartNotNumeric => "La colonne « {} » n'est pas une colonne numérique",
+        Msg::ChartNoProvinces => "Veuillez fournir au moins une province à tracer",
+        Msg::SummaryRows => "lignes",
+        Msg::ProvinceNotFound => "Aucune ligne trouvée pour la province « {} »",
+        Msg::ProblemsFound => "{} problème(s) trouvé(s) dans {} lignes",
+        Msg::SearchInvalid => "Paramètre de recherche invalide. Veuillez suivre l'exemple suivant (sans guillemets) : « 4, 7, 9-14 »",
+        Msg::QueryInvalid => "Condition de recherche invalide. Veuillez suivre l'exemple suivant (sans guillemets) : « pruid=35, date=2020-03-01..2020-03-31, numtoday>100 »",
+        Msg::QueryUnknownAbbr => "Abréviation de province inconnue « {} »",
+        Msg::QueryBadColumn => "Impossible de chercher par la colonne « {} » (utilisez pruid, prname, abbr ou date)",
+        Msg::QueryNotNumber => "Impossible de comparer la colonne « {} » avec « {} » (doit être un nombre)",
+        Msg::QueryNotNumericColumn => "Impossible de comparer la colonne « {} » (doit être une colonne numérique)",
+        Msg::EditPrompt => "Choisissez une colonne (1-10) à modifier parmi les suivantes (ligne {}). Entrez Q pour quitter.",
+        Msg::EditPruidPrompt => "Choisissez une nouvelle valeur pour cette ligne (3 chiffres max.)",
+        Msg::EditNamePrompt => "Choisissez une nouvelle valeur pour cette ligne (30 caractères max.)",
+        Msg::EditDatePrompt => "Choisissez une nouvelle valeur pour cette ligne (15 caractères max.)",
+        Msg::EditCountPrompt => "Choisissez une nouvelle valeur pour cette ligne (8 chiffres max.)",
+        Msg::EditRatePrompt => "Choisissez une nouvelle valeur pour cette ligne (99999.99 max., deux décimales au plus)",
+        Msg::EditColumnInvalid => "Veuillez choisir un numéro valide (1-10)",
+        Msg::ColumnUnknown => "Il n'y a pas de colonne « {} » (les colonnes sont {}, ou 1-10)",
+        Msg::SchemaMissing => "{} : une valeur est obligatoire",
+        Msg::SchemaNotInteger => "{} : « {} » n'est pas un nombre entier",
+        Msg::SchemaNotNumber => "{} : « {} » n'est pas un nombre",
+        Msg::SchemaNotDate => "{} : « {} » n'est pas une date AAAA-MM-JJ valide",
+        Msg::SchemaTooSmall => "{} : {} est inférieur au minimum de {}",
+        Msg::SchemaTooLarge => "{} : {} dépasse le maximum de {}",
+        Msg::SchemaTooLong => "{} : « {} » dépasse {} caractères",
+        Msg::SchemaPrecision => "{} : {} a plus de {} chiffre(s) après la virgule",
+        Msg::SchemaPattern => "{} : « {} » ne correspond pas au motif {}",
+        Msg::SchemaNotAllowed => "{} : « {} » ne fait pas partie de {}",
+        Msg::LoadRowInvalid => "Fichier {}, ligne {} : {}",
+        Msg::LoadRowColumns => "Fichier {}, ligne {} : {} valeurs attendues, {} trouvées",
+        Msg::SaveRowInvalid => "La ligne {} ne peut pas être enregistrée : {}",
+        Msg::LockWaiting => "En attente d'une autre session qui utilise {}...",
+        Msg::SaveConflict => "{} a été enregistré par une autre session depuis son chargement",
+        Msg::SaveConflictPrompt => "R pour le recharger (en abandonnant les changements faits ici), M pour fusionner ses changements avec les vôtres, O pour l'écraser, ou ENTRÉE pour annuler : ",
+        Msg::SaveReloaded => "{} lignes rechargées depuis {}",
+        Msg::SaveCancelled => "Rien n'a été enregistré",
+        Msg::EditRowInvalid => "Il n'y a pas de ligne {} (le tableau compte {} lignes)",
+        Msg::EditKeepGoing => "Voulez-vous continuer à modifier? Entrez Q pour quitter (toute autre touche pour continuer)",
+        Msg::DeletePrompt => "Voulez-vous supprimer la ligne suivante (ligne {})? o/N",
+        Msg::FileEmpty => "Contenu du fichier invalide : le fichier doit contenir au moins une ligne de texte",
+        Msg::FileFormatUnknown => "Le fichier {} n'est ni un téléchargement source ni un fichier de données enregistré",
+        Msg::PopulationLineInvalid => "Fichier de population invalide : la ligne {} doit avoir 2 colonnes (pruid,population)",
+        Msg::SeriesDateInvalid => "La ligne {} a une date invalide « {} »",
+        Msg::RuleTotalSum => "numtotal {} n'est pas numconf {} + numprob {}",
+        Msg::RuleProvinceName => "le nom « {} »/« {} » diffère de « {} »/« {} » utilisé par les autres lignes du pruid {}",
+        Msg::RuleDailyChange => "numtoday {} ne correspond pas à la variation de numtotal ({} à {})",
+        Msg::RuleCumulative => "{} a diminué de {} le {} à {}",
+        Msg::ViolationRow => "Ligne {} ({} {}) : {}",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::logic::Lang;
+    use crate::messages::{self, Msg};
+
+    #[test]
+    fn test_catalog_complete() {
+        //ALL lists each key once, so every key below is checked
+        let keys: HashSet<Msg> = messages::ALL.iter().copied().collect();
+        assert_eq!(keys.len(), messages::ALL.len());
+
+        for msg in messages::ALL.iter() {
+            let en = messages::text(&Lang::EN, *msg);
+            let fr = messages::text(&Lang::FR, *msg);
+            assert!(!en.is_empty(), "{:?} has no English text", msg);
+            assert!(!fr.is_empty(), "{:?} has no French text", msg);
+            assert_eq!(en.matches("{}").count(), fr.matches("{}").count(), "{:?} has different placeholders in English and French", msg);
+        }
+    }
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(messages::fill(&Lang::EN, Msg::PageStatus, &[&2, &4, &26, &50, &100]), "Page 2 of 4, rows 26-50 of 100");
+        assert_eq!(messages::fill(&Lang::FR, Msg::ProblemsFound, &[&3, &100]), "3 problème(s) trouvé(s) dans 100 lignes");
+        assert!(messages::is_yes("O") && messages::is_yes("y") && !messages::is_yes(""));
+    }
+}
@@ -0,0 +1,268 @@
+//Final Project - Thomas Ivanov
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+use crate::datastore::DataRow;
+use crate::index::Index;
+use crate::logic::Lang;
+use crate::merge::{self, Key};
+use crate::messages::{self, Msg};
+use crate::province;
+use crate::schema::{ColumnError, SCHEMA};
+
+/// Names of the stored columns, in the order of `DataRow::public_vec`. They are numbered from 1 when editing.
+pub const STORED_COLUMNS: [&str; 10] = ["pruid", "prname", "prname_fr", "date", "numconf", "numprob", "numdeaths", "numtotal", "numtoday", "ratetotal"];
+
+/// Finds a stored column by its name or number (1-10), returning its index in `STORED_COLUMNS`.
+pub fn stored_column(column: &str) -> Option<usize> {
+    let column = column.trim().to_lowercase();
+    match column.parse::<usize>() {
+        Ok(n) if (1..=STORED_COLUMNS.len()).contains(&n) => Some(n - 1),
+        Ok(_) => None,
+        Err(_) if column == "prnamefr" => Some(2),
+        Err(_) => STORED_COLUMNS.iter().position(|c| *c == column),
+    }
+}
+
+/// Why an edit or delete was refused. Nothing is changed when one of these is returned.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditError {
+    /// The column is neither a stored column name nor a number from 1 to 10.
+    UnknownColumn(String),
+    /// The value breaks a constraint of its column in `schema::SCHEMA`.
+    Invalid(ColumnError),
+    /// There is no row at this position in a table of the given length.
+    NoSuchRow(usize, usize),
+}
+
+impl EditError {
+    /// Describes the error in a language.
+    pub fn describe(&self, lang: &Lang) -> String {
+        match self {
+            EditError::UnknownColumn(column) => messages::fill(lang, Msg::ColumnUnknown, &[column, &STORED_COLUMNS.join(", ")]),
+            EditError::Invalid(e) => e.describe(lang),
+            EditError::NoSuchRow(row, len) => messages::fill(lang, Msg::EditRowInvalid, &[row, len]),
+        }
+    }
+}
+
+impl From<ColumnError> for EditError {
+    fn from(e: ColumnError) -> EditError {
+        EditError::Invalid(e)
+    }
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.describe(&Lang::EN))
+    }
+}
+
+impl Error for EditError {}
+
+/// Checks a new value for a stored column (by name or number) against its constraints in `schema::SCHEMA`
+/// and writes it to the row.
+///
+/// A province name can also be given as an abbreviation or in the other language. Setting the `pruid` or
+/// either name sets all three from the province reference table. An empty count or rate is stored as 0.
+///
+/// # Examples
+///
+/// ```
+/// set_field(&mut data[4], "numtoday", "412")?;
+/// assert!(set_field(&mut data[4], "9", "many").is_err());
+/// ```
+pub fn set_field(row: &mut DataRow, column: &str, value: &str) -> Result<(), EditError> {
+    let column = stored_column(column).ok_or_else(|| EditError::UnknownColumn(column.trim().to_string()))?;
+    let value = value.trim();
+
+    //Any way of writing a province name is accepted, and anything else is checked (and refused) as it is
+    let named = match column {
+        1 | 2 => province::by_abbr(value).or_else(|| province::by_name(value)),
+        _ => None,
+    };
+    if named.is_none() {
+        SCHEMA[column].check(value)?;
+    }
+
+    //The value was checked, so it can be read without errors
+    let count = || value.parse::<isize>().unwrap_or(0);
+    match column {
+        0 => province::assign(row, province::by_pruid(count()).unwrap()),
+        1 | 2 => named.iter().for_each(|p| province::assign(row, p)),
+        3 => row.date = value.to_string(),
+        4 => row.numconf = count(),
+        5 => row.numprob = count(),
+        6 => row.numdeaths = count(),
+        7 => row.numtotal = count(),
+        8 => row.numtoday = count(),
+        _ => row.ratetotal = value.parse::<f64>().unwrap_or(0.0),
+    }
+    Ok(())
+}
+
+/// Builds a new row from column values (by name or number), each checked by `set_field`. The `pruid` (or a
+/// province name) and the `date` must be given, and the counts and rate that aren't are 0.
+pub fn new_row(fields: &[(String, String)]) -> Result<DataRow, EditError> {
+    let given = |columns: &[usize]| fields.iter().any(|(c, _)| stored_column(c).is_some_and(|c| columns.contains(&c)));
+    for (key, columns) in [(0, [0, 1, 2].as_slice()), (3, [3].as_slice())] {
+        if !given(columns) {
+            SCHEMA[key].check("")?;
+        }
+    }
+    let mut row = DataRow::new(vec!["0", "", "", "", "0", "0", "0", "0", "0", "0"]);
+    for (column, value) in fields.iter() {
+        set_field(&mut row, column, value)?;
+    }
+    Ok(row)
+}
+
+/// Finds the row with a `pruid` and `date` through the indexes.
+pub fn find_row(index: &Index, key: &Key) -> Option<usize> {
+    let dates = index.date_range(&key.1, &key.1);
+    index.pruid(key.0).into_iter().find(|pos| dates.contains(pos))
+}
+
+/// Sets a field of the row at position `pos` in the table, keeping the indexes and the set of locally
+/// edited rows in step. The row is left as it was if the value is refused.
+pub fn edit_row(data: &mut [DataRow], index: &mut Index, edited: &mut BTreeSet<Key>, pos: usize, column: &str, value: &str) -> Result<(), EditError> {
+    if pos >= data.len() {
+        return Err(EditError::NoSuchRow(pos, data.len()));
+    }
+    let mut row = data[pos].clone();
+    set_field(&mut row, column, value)?;
+    index.update(pos, &data[pos], &row);
+    //Both keys are marked, in case the pruid or date was changed, so a merge won't overwrite the edit
+    edited.insert(merge::key(&data[pos]));
+    edited.insert(merge::key(&row));
+    data[pos] = row;
+    Ok(())
+}
+
+/// Adds a row at the end of the table, keeping the indexes and the set of locally edited rows in step.
+/// The row should have been built with `new_row` so its values are checked.
+pub fn insert_row(data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>, row: DataRow) {
+    index.insert(data.len(), &row);
+    edited.insert(merge::key(&row));
+    data.push(row);
+}
+
+/// Deletes the rows at the given positions from the table, keeping the indexes and the set of locally
+/// edited rows in step, and returns them in table order. Nothing is deleted if any position is past the end.
+///
+/// # Examples
+///
+/// ```
+/// let removed = delete_rows(&mut data, &mut index, &mut edited, &[4, 7])?;
+/// ```
+pub fn delete_rows(data: &mut Vec<DataRow>, index: &mut Index, edited: &mut BTreeSet<Key>, ids: &[usize]) -> Result<Vec<DataRow>, EditError> {
+    if let Some(&pos) = ids.iter().find(|&&pos| pos >= data.len()) {
+        return Err(EditError::NoSuchRow(pos, data.len()));
+    }
+    let mut ids = ids.to_vec();
+    ids.sort_unstable();
+    ids.dedup();
+
+    //Rows are removed from the end so the positions still to be removed don't move
+    let mut removed: Vec<DataRow> = Vec::new();
+    for &pos in ids.iter().rev() {
+        let row = data.remove(pos);
+        index.remove(pos, &row);
+        //A merge won't add the row back
+        edited.insert(merge::key(&row));
+        removed.push(row);
+    }
+    removed.reverse();
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use crate::datastore::DataRow;
+    use crate::index::Index;
+    use crate::logic::Lang;
+    use crate::merge::Key;
+    use crate::ops::{self, EditError};
+    use crate::province;
+    use crate::schema::Broken;
+
+    fn fields(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+        fields.iter().map(|(c, v)| (c.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_set_field() {
+        let mut row = DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "1", "10", "10", "0.1"]);
+        ops::set_field(&mut row, "numtoday", "412").unwrap();
+        ops::set_field(&mut row, "10", "12.25").unwrap();
+        ops::set_field(&mut row, "prname", "qc").unwrap();
+        assert_eq!((row.numtoday, row.ratetotal, row.pruid, row.prname_fr.as_str()), (412, 12.25, 24, "Québec"));
+        ops::set_field(&mut row, "numdeaths", "").unwrap();
+        assert_eq!(row.numdeaths, 0);
+    }
+
+    #[test]
+    fn test_set_field_refused() {
+        let mut row = DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "1", "10", "10", "0.1"]);
+        let refused = [("pruid", "1000", Broken::TooLarge(999.0)), ("prname_fr", "Atlantis", Broken::NotAllowed(province::names_fr().join(", "))),
+                       ("date", "2020-02-30", Broken::NotDate), ("numconf", "123456789", Broken::TooLarge(99999999.0)),
+                       ("numdeaths", "-1", Broken::TooSmall(0.0)), ("ratetotal", "1.234", Broken::Precision(2))];
+        for (column, value, broken) in refused {
+            let before = row.clone();
+            match ops::set_field(&mut row, column, value) {
+                Err(EditError::Invalid(e)) => assert_eq!((e.column, e.broken), (column, broken)),
+                other => panic!("{} = {}: {:?}", column, value, other),
+            }
+            assert_eq!(row, before);
+        }
+        assert_eq!(ops::set_field(&mut row, "11", "0"), Err(EditError::UnknownColumn(String::from("11"))));
+    }
+
+    #[test]
+    fn test_describe_unknown_column() {
+        let error = EditError::UnknownColumn(String::from("numdaeths"));
+        assert_eq!(error.describe(&Lang::EN), "There is no column \"numdaeths\" (the columns are pruid, prname, prname_fr, date, numconf, numprob, numdeaths, numtotal, numtoday, ratetotal, or 1-10)");
+        assert_eq!(error.describe(&Lang::FR), "Il n'y a pas de colonne « numdaeths » (les colonnes sont pruid, prname, prname_fr, date, numconf, numprob, numdeaths, numtotal, numtoday, ratetotal, ou 1-10)");
+    }
+
+    #[test]
+    fn test_new_row() {
+        let new = ops::new_row(&fields(&[("prname", "NS"), ("date", "2020-03-09"), ("numconf", "3")])).unwrap();
+        assert_eq!((new.pruid, new.numconf, new.numtotal), (12, 3, 0));
+        match ops::new_row(&fields(&[("pruid", "12")])) {
+            Err(EditError::Invalid(e)) => assert_eq!((e.column, e.broken), ("date", Broken::Missing)),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_edit_row() {
+        let row = |date: &str| DataRow::new(vec!["35", "Ontario", "Ontario", date, "12", "0", "1", "12", "2", "0.1"]);
+        let mut data = vec![row("2020-03-01"), row("2020-03-02")];
+        let mut index = Index::new(&data);
+        let mut edited: BTreeSet<Key> = BTreeSet::new();
+        ops::edit_row(&mut data, &mut index, &mut edited, 1, "date", "2020-03-05").unwrap();
+        assert_eq!(index.date_range("2020-03-05", "2020-03-05"), vec![1]);
+        assert_eq!(edited.len(), 2);
+        assert_eq!(ops::edit_row(&mut data, &mut index, &mut edited, 2, "numtoday", "1"), Err(EditError::NoSuchRow(2, 2)));
+    }
+
+    #[test]
+    fn test_delete_rows() {
+        let row = |date: &str| DataRow::new(vec!["35", "Ontario", "Ontario", date, "12", "0", "1", "12", "2", "0.1"]);
+        let mut data = vec![row("2020-03-01"), row("2020-03-02"), row("2020-03-03")];
+        let mut index = Index::new(&data);
+        let mut edited: BTreeSet<Key> = BTreeSet::new();
+        assert_eq!(ops::delete_rows(&mut data, &mut index, &mut edited, &[0, 5]), Err(EditError::NoSuchRow(5, 3)));
+        assert_eq!(data.len(), 3);
+        let removed = ops::delete_rows(&mut data, &mut index, &mut edited, &[2, 0, 2]).unwrap();
+        assert_eq!((removed.len(), data.len()), (2, 1));
+        assert_eq!(data[0].date, "2020-03-02");
+        assert_eq!(index, Index::new(&data));
+        assert_eq!(edited.len(), 2);
+    }
+}
@@ -0,0 +1,179 @@
+//Final Project - Thomas Ivanov
+
+use std::ops::Range;
+
+use crate::logic::Config;
+use crate::messages::Msg;
+
+/// Number of rows shown on a page unless the configuration says otherwise.
+pub const DEFAULT_PAGE_SIZE: usize = 25;
+
+/// A navigation command read from the user at the bottom of a page.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Nav {
+    /// Enter or N: the next page, leaving the pager after the last one.
+    Next,
+    /// P: the previous page.
+    Previous,
+    /// F: the first page.
+    First,
+    /// L: the last page.
+    Last,
+    /// G followed by a page number, or just a page number: that page.
+    Goto(usize),
+    /// Z followed by a number: show that many rows per page.
+    Size(usize),
+    /// Q: leave the pager.
+    Quit,
+    /// Anything else, lowercased and trimmed, for the caller to handle (such as S to sort).
+    Other(String),
+}
+
+/// Reads a navigation command from a line of user input.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(parse(""), Nav::Next);
+/// assert_eq!(parse("g 4"), Nav::Goto(4));
+/// assert_eq!(parse("s"), Nav::Other(String::from("s")));
+/// ```
+pub fn parse(input: &str) -> Nav {
+    let input = input.trim().to_lowercase();
+    if let Ok(page) = input.parse::<usize>() {
+        return Nav::Goto(page);
+    }
+    let (key, arg) = match input.char_indices().nth(1) {
+        Some((i, _)) => (&input[..i], input[i..].trim().parse::<usize>().ok()),
+        None => (input.as_str(), None),
+    };
+    match (key, arg) {
+        ("", _) | ("n", None) => Nav::Next,
+        ("p", None) => Nav::Previous,
+        ("f", None) => Nav::First,
+        ("l", None) => Nav::Last,
+        ("g", Some(page)) => Nav::Goto(page),
+        ("z", Some(size)) if size > 0 => Nav::Size(size),
+        ("q", None) => Nav::Quit,
+        _ => Nav::Other(input),
+    }
+}
+
+/// Tracks the current page of a paginated view over `rows` rows.
+///
+/// Pages are numbered from 1 for the user, and `page` is the index of the current one (from 0).
+///
+/// # Examples
+///
+/// ```
+/// let mut pager = Pager::new(data.len(), config.page_size);
+/// while pager.is_open() {
+///     for row in data[pager.range()].iter() { ... }
+///     println!("{}", pager.status(&config));
+///     pager.apply(&pager::parse(&input));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pager {
+    pub page: usize,
+    pub page_size: usize,
+    pub rows: usize,
+    open: bool,
+}
+
+impl Pager {
+    /// A function for creating a new `Pager` on the first page. A page size of 0 is taken as the default size.
+    pub fn new(rows: usize, page_size: usize) -> Pager {
+        Pager {
+            page: 0,
+            page_size: if page_size == 0 { DEFAULT_PAGE_SIZE } else { page_size },
+            rows,
+            open: rows > 0,
+        }
+    }
+
+    /// Number of pages (at least 1, so an empty view still has a page to show).
+    pub fn pages(&self) -> usize {
+        self.rows.div_ceil(self.page_size).max(1)
+    }
+
+    /// Indices of the rows on the current page.
+    pub fn range(&self) -> Range<usize> {
+        let start = (self.page * self.page_size).min(self.rows);
+        start..(start + self.page_size).min(self.rows)
+    }
+
+    /// Returns false once the user has left the pager (Q, or Enter on the last page).
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Moves to the first page, e.g. after the rows have been sorted.
+    pub fn first(&mut self) {
+        self.page = 0;
+    }
+
+    /// Applies a navigation command, returning false if it isn't one (`Nav::Other`) so the caller can handle it.
+    /// Page numbers past either end are clamped to the first or last page.
+    pub fn apply(&mut self, nav: &Nav) -> bool {
+        let last = self.pages() - 1;
+        match nav {
+            Nav::Next => if self.page < last { self.page += 1 } else { self.open = false },
+            Nav::Previous => self.page = self.page.saturating_sub(1),
+            Nav::First => self.page = 0,
+            Nav::Last => self.page = last,
+            Nav::Goto(page) => self.page = page.saturating_sub(1).min(last),
+            Nav::Size(size) => {
+                //Stay on the page showing the first row of the current page
+                let first_row = self.page * self.page_size;
+                self.page_size = *size;
+                self.page = (first_row / self.page_size).min(self.pages() - 1);
+            }
+            Nav::Quit => self.open = false,
+            Nav::Other(_) => return false,
+        }
+        true
+    }
+
+    /// The status line for the current page, e.g. "Page 2 of 4, rows 26-50 of 100".
+    pub fn status(&self, config: &Config) -> String {
+        let range = self.range();
+        let first = if range.is_empty() { 0 } else { range.start + 1 };
+        config.fill(Msg::PageStatus, &[&(self.page + 1), &self.pages(), &first, &range.end, &self.rows])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::pager::{self, Nav, Pager};
+
+    #[test]
+    fn test_pager_navigation() {
+        assert_eq!(pager::parse(" "), Nav::Next);
+        assert_eq!(pager::parse("P"), Nav::Previous);
+        assert_eq!(pager::parse("3"), Nav::Goto(3));
+        assert_eq!(pager::parse("g 3"), Nav::Goto(3));
+        assert_eq!(pager::parse("z10"), Nav::Size(10));
+        assert_eq!(pager::parse("z 0"), Nav::Other(String::from("z 0")));
+        assert_eq!(pager::parse("R"), Nav::Other(String::from("r")));
+
+        let mut pager = Pager::new(60, 25);
+        assert_eq!(pager.pages(), 3);
+        assert!(pager.apply(&Nav::Last));
+        assert_eq!(pager.range(), 50..60);
+        assert!(pager.apply(&Nav::Previous));
+        assert_eq!(pager.range(), 25..50);
+        assert!(pager.apply(&Nav::Goto(99)));
+        assert_eq!(pager.page, 2);
+
+        //Row 51 is on page 6 of 10-row pages
+        assert!(pager.apply(&Nav::Size(10)));
+        assert_eq!((pager.page, pager.pages()), (5, 6));
+        assert!(!pager.apply(&Nav::Other(String::from("s"))));
+
+        assert!(pager.apply(&Nav::Last));
+        assert!(pager.apply(&Nav::Next));
+        assert!(!pager.is_open());
+        assert!(!Pager::new(0, 25).is_open());
+    }
+}
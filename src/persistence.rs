@@ -2,43 +2,86 @@
 
 use std::fs;
 use std::error::Error;
-use std::convert::TryInto;
-use std::io::prelude::*;
+use std::io::{self, prelude::*};
+use std::collections::{BTreeSet, HashMap};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::RwLock;
+use std::time::SystemTime;
 
+use crate::arrow;
+use crate::batch::{self, Command};
+use crate::csv;
 use crate::datastore::*;
-use crate::logic::Config;
+use crate::export;
+use crate::ini::Ini;
+use crate::merge::Key;
+use crate::logic::{Config, Lang};
+use crate::messages::{self, Msg};
+use crate::province;
+use crate::rates::Population;
+use crate::schema::{self, ColumnError, Kind, SCHEMA};
+use crate::sql;
+use crate::views::{self, Views};
+
+/// Settings file in the project root, holding the user's defaults (see `Config`) and saved views.
+pub const SETTINGS_FILE: &str = "tabledb.ini";
+
+/// Number of rows `load_data` takes from a source download, which is enough to start a working file with.
+pub const FIRST_IMPORT_ROWS: usize = 100;
 
 /// Load the data from the csv file into a vector.
 /// 
 /// A Config struct is used to determine which file and which language setting to use for data IO. A Result
 /// type is returned to pass possible errors up the stack to be safely handled by the calling function.
 /// 
+/// Province names are normalized against the province reference table as the rows are read, and every value
+/// is checked against the column constraints in `schema::SCHEMA`. Only the first `FIRST_IMPORT_ROWS` rows of
+/// a source download are read: use `load_all_data` to read all of them.
+/// 
 /// # Examples
 /// 
 /// ```
 /// let data = load_data(&config).unwrap();
 /// ```
 pub fn load_data(config: &Config) -> Result<(Header, Vec<DataRow>), Box<dyn Error>> {
+    load_rows(config, Some(FIRST_IMPORT_ROWS))
+}
+
+/// Load every row of the data file, like `load_data` but without its limit on source downloads, for
+/// comparing or merging a whole download.
+pub fn load_all_data(config: &Config) -> Result<(Header, Vec<DataRow>), Box<dyn Error>> {
+    load_rows(config, None)
+}
+
+/// Does the work of `load_data`, reading at most `limit` rows of a source download.
+fn load_rows(config: &Config, limit: Option<usize>) -> Result<(Header, Vec<DataRow>), Box<dyn Error>> {
+    //SQL scripts (such as the ones written by save_sql) are read through their INSERT statements, and Arrow files by column name
+    match std::path::Path::new(&config.filename).extension().map(|e| e.to_string_lossy().to_lowercase()).as_deref() {
+        Some("sql") => return load_sql(config),
+        Some("arrow" | "feather" | "ipc") => return load_arrow(config),
+        _ => {}
+    }
 
     //Match-extract the data from Ok(data) (which was output by the read fn), or else propagate Err(e) to main()
     let contents = fs::read_to_string(config.filename.clone())?;
-    let column_labels: Header;
+    //A byte order mark (written for spreadsheets) isn't part of the header, and the delimiter is whichever the header uses
+    let contents = csv::strip_bom(&contents);
+    let header = match contents.lines().next() {
+        Some(v) => v,
+        None => return Err(config.text(Msg::FileEmpty).into()),
+    };
+    let delimiter = csv::detect_delimiter(header);
+    let format = match file_format(config, header, delimiter) {
+        Some(format) => format,
+        None => return Err(config.fill(Msg::FileFormatUnknown, &[&config.filename]).into()),
+    };
+
     //Call default filter on contents String for first row/line (column labels), then filter by language
-    if config.filename == String::from("covid19-download.csv") {
-        column_labels = match contents.lines().next() {
-            Some(v) => Header::new(default_filter(v).map(|e| e.to_string()).collect()),
-            None => return Err("File contents invalid: File must have at least one line of text".into()),
-        };
-    }
-    else if config.filename == String::from("datastore.csv") {
-        column_labels = match contents.lines().next() {
-            Some(v) => Header::new(v.split(",").map(|e| e.to_string()).collect()),
-            None => return Err("File contents invalid: File must have at least one line of text".into()),
-        };
-    }
-    else {
-        return Err("No valid filename".into())
-    }
+    let column_labels = match format {
+        Format::Source => Header::new(default_filter(header).map(|e| e.to_string()).collect()),
+        Format::Store => Header::new(csv::split_line(header, delimiter)),
+    };
 
     //Reassign contents as iterator without column labels line
     let contents = contents.lines().enumerate()
@@ -49,65 +92,166 @@ pub fn load_data(config: &Config) -> Result<(Header, Vec<DataRow>), Box<dyn Erro
     //Format the collected lines for presentation, assemble them in structs (Vector used purely as intermediary), and print to screen
     let mut data: Vec<DataRow> = Vec::new();
     
-    if config.filename == String::from("covid19-download.csv") {
-        let mut line_count = 0;
-        for line in contents.lines() {
+    if format == Format::Source {
+        for (i, line) in contents.lines().enumerate().take(limit.unwrap_or(usize::MAX)) {
             let line = default_filter(line);
 
             let line: Vec<&str> = line.collect();
 
-            let line = DataRow::new(line);
+            let line = read_row(&line, i + 2, config)?;
 
             data.push(line);
-
-            line_count += 1;
-            if line_count >= 100 {break}
         }
     }
-    else if config.filename == String::from("datastore.csv") {
-        for line in contents.lines() {
-            let line = line.split(",");
+    else {
+        for (i, line) in contents.lines().enumerate() {
+            let line = csv::split_line(line, delimiter);
 
-            let line: Vec<&str> = line.collect();
+            let line: Vec<&str> = line.iter().map(|e| e.as_str()).collect();
 
-            let line = DataRow::new(line);
+            let line = read_row(&line, i + 2, config)?;
 
             data.push(line);
         }
     }
-    else {
-        return Err("No valid filename".into())
-    }
 
     Ok((column_labels, data))
 }
 
-/// Save the current state of the data in memory to the working CSV file.
+/// Load the rows of an SQL script (`config.filename`), as written by `save_sql`, checking every row like
+/// the rows of a CSV file. The column labels are the names of the stored columns.
+fn load_sql(config: &Config) -> Result<(Header, Vec<DataRow>), Box<dyn Error>> {
+    let contents = fs::read_to_string(&config.filename)?;
+    let rows = sql::parse(&contents, &config.language).map_err(|e| config.fill(Msg::LoadFileInvalid, &[&config.filename, &e]))?;
+    let mut data: Vec<DataRow> = Vec::new();
+    for (line, fields) in rows.iter() {
+        let fields: Vec<&str> = fields.iter().map(|f| f.as_str()).collect();
+        data.push(read_row(&fields, *line, config)?);
+    }
+    Ok((Header::new(SCHEMA.iter().map(|c| c.name.to_string()).collect()), data))
+}
+
+/// Load the rows of an Arrow IPC file (`config.filename`), as written by `save_arrow`, checking every row
+/// like the rows of a CSV file. The column labels are the names of the stored columns.
+fn load_arrow(config: &Config) -> Result<(Header, Vec<DataRow>), Box<dyn Error>> {
+    let rows = arrow::read(fs::File::open(&config.filename)?, &config.language).map_err(|e| config.fill(Msg::LoadFileInvalid, &[&config.filename, &e]))?;
+    let mut data: Vec<DataRow> = Vec::new();
+    for (i, fields) in rows.iter().enumerate() {
+        let fields: Vec<&str> = fields.iter().map(|f| f.as_str()).collect();
+        data.push(read_row(&fields, i + 1, config)?);
+    }
+    Ok((Header::new(SCHEMA.iter().map(|c| c.name.to_string()).collect()), data))
+}
+
+/// Reads the stored values of a line (line `line` of the file) into a data row, checking them against
+/// `schema::SCHEMA`. Names are normalized before they are checked against the reference table, so files
+/// saved with other spellings still load.
+fn read_row(fields: &[&str], line: usize, config: &Config) -> Result<DataRow, Box<dyn Error>> {
+    if fields.len() != SCHEMA.len() {
+        return Err(config.fill(Msg::LoadRowColumns, &[&config.filename, &line, &SCHEMA.len(), &fields.len()]).into());
+    }
+    let invalid = |e: ColumnError| config.fill(Msg::LoadRowInvalid, &[&config.filename, &line, &e.describe(&config.language)]);
+    //Rates saved with a decimal comma (for spreadsheets in French) are read with a point
+    let fields: Vec<String> = SCHEMA.iter().zip(fields.iter()).map(|(c, f)| if c.kind == Kind::Decimal { f.replacen(',', ".", 1) } else { f.to_string() }).collect();
+    let fields: Vec<&str> = fields.iter().map(|f| f.as_str()).collect();
+    schema::check_fields(&fields).map_err(invalid)?;
+    let mut row = DataRow::new(fields);
+    province::normalize(&mut row);
+    schema::check_row(&row).map_err(invalid)?;
+    Ok(row)
+}
+
+/// Layout of a data file: a source download from PHAC, or a saved working file with only the stored columns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Source,
+    Store,
+}
+
+/// Works out the layout of a data file from the number of columns in its header line. A header that fits
+/// neither layout is taken to be the layout of the configured input or output file if it is one of them, so
+/// its rows are reported as having the wrong number of columns.
+fn file_format(config: &Config, header: &str, delimiter: char) -> Option<Format> {
+    match csv::split_line(header, delimiter).len() {
+        10 => Some(Format::Store),
+        n if n >= 16 => Some(Format::Source),
+        _ if config.filename == config.input => Some(Format::Source),
+        _ if config.filename == config.output => Some(Format::Store),
+        _ => None,
+    }
+}
+
+/// Save rows of cells to a CSV file with a header line, quoting cells that contain commas, quotes or line breaks.
+pub fn save_table(filename: &str, header: &[String], rows: &[Vec<String>]) -> Result<(), Box<dyn Error>> {
+    let mut s = String::new();
+    for cells in std::iter::once(header).chain(rows.iter().map(|r| r.as_slice())) {
+        let line: Vec<String> = cells.iter().map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) { format!("\"{}\"", cell.replace('"', "\"\"")) } else { cell.clone() }
+        }).collect();
+        s.push_str(&line.join(","));
+        s.push('\n');
+    }
+    fs::write(filename, s)?;
+    Ok(())
+}
+
+/// Export rows of cells with a header line to a Markdown, HTML or LaTeX file, in the format given by the file
+/// name's extension (see `export::Format`). The title heads the HTML page.
+pub fn export_table(filename: &str, title: &str, header: &[String], rows: &[Vec<String>], config: &Config) -> Result<(), Box<dyn Error>> {
+    let format = export::Format::from_filename(filename).ok_or_else(|| config.fill(Msg::ExportFormatUnknown, &[&filename]))?;
+    fs::write(filename, export::render(format, title, header, rows))?;
+    Ok(())
+}
+
+/// Save the data as an SQL script that creates and fills a table for SQLite or PostgreSQL (see `sql::dump`),
+/// named after the file. Returns the name of the table. Nothing is written if a row breaks a column constraint.
+pub fn save_sql(filename: &str, data: &[DataRow], config: &Config) -> Result<String, Box<dyn Error>> {
+    for (i, row) in data.iter().enumerate() {
+        schema::check_row(row).map_err(|e| config.fill(Msg::SaveRowInvalid, &[&i, &e.describe(&config.language)]))?;
+    }
+    let table = sql::table_name(filename);
+    fs::write(filename, sql::dump(&table, data))?;
+    Ok(table)
+}
+
+/// Save the data as an Arrow IPC file with a typed schema (see `arrow::write`), for analytics tools such as
+/// pandas and polars. Nothing is written if a row breaks a column constraint.
+pub fn save_arrow(filename: &str, data: &[DataRow], config: &Config) -> Result<(), Box<dyn Error>> {
+    for (i, row) in data.iter().enumerate() {
+        schema::check_row(row).map_err(|e| config.fill(Msg::SaveRowInvalid, &[&i, &e.describe(&config.language)]))?;
+    }
+    arrow::write(data, fs::File::create(filename)?)
+}
+
+/// Save the current state of the data in memory to the working CSV file (`config.output`).
 /// 
 /// A string `s` is constructed by combining the header line with the output of all the
 /// data rows (looped through and appended), with the data from each column treated as
-/// strings.
-pub fn save_data(column_labels: &Header, data: &Vec<DataRow>) -> Result<(), Box<dyn Error>> {
+/// strings. The delimiter, decimal separator, line endings, quoting and byte order mark are
+/// taken from `config.csv`. Nothing is written if a row breaks a column constraint.
+pub fn save_data(column_labels: &Header, data: &[DataRow], config: &Config) -> Result<(), Box<dyn Error>> {
+    //Rows are checked against the schema so a saved file can always be loaded again
+    for (i, row) in data.iter().enumerate() {
+        schema::check_row(row).map_err(|e| config.fill(Msg::SaveRowInvalid, &[&i, &e.describe(&config.language)]))?;
+    }
+
+    let format = &config.csv;
+    let numeric: Vec<bool> = SCHEMA.iter().map(|c| matches!(c.kind, Kind::Integer | Kind::Decimal)).collect();
     let mut s = String::new();
-    for label in column_labels.clone().labels.iter() {
-        s.push_str(label);
-        s.push_str(",");
+    if format.bom {
+        s.push(csv::BOM);
     }
-    s.pop();
-    s.push_str("\n");
+    s.push_str(&format.write_line(&column_labels.labels, &[]));
+    s.push_str(format.line_ending());
     let mut s2 = String::new();
 
     for col in data {
-        for item in col.public_vec() {
-            s2.push_str(&item);
-            s2.push_str(",");
-        }
-        s2.pop();
-        s2.push_str("\n");
+        s2.push_str(&format.write_line(&col.public_vec(), &numeric));
+        s2.push_str(format.line_ending());
     }
 
     s.push_str(&s2);
-    let mut file = fs::File::create("datastore.csv")?;
+    let mut file = fs::File::create(&config.output)?;
     file.write_all(s.as_bytes())?;
     Ok(())
 }
@@ -115,29 +259,224 @@ pub fn save_data(column_labels: &Header, data: &Vec<DataRow>) -> Result<(), Box<
 /// Replaces memory with the data from the original source file, in case
 /// of needing to refresh the working data set.
 pub fn refresh_data(config: &mut Config) -> Result<(Header, Vec<DataRow>), Box<dyn Error>> {
-    config.change_file(String::from("covid19-download.csv"));
+    config.change_file(config.input.clone());
     let both = load_data(config)?;
     Ok(both)
 }
 
+/// Name of the file listing the rows edited or deleted locally, kept next to the working file.
+pub fn edits_file(config: &Config) -> String {
+    format!("{}.edits", config.output)
+}
+
+/// Load the keys (`pruid`, `date`) of the rows that were edited or deleted locally, so a merge can leave them alone.
+/// There are none if the edits file doesn't exist.
+pub fn load_edits(config: &Config) -> Result<BTreeSet<Key>, Box<dyn Error>> {
+    let filename = edits_file(config);
+    let mut edited: BTreeSet<Key> = BTreeSet::new();
+    if fs::metadata(&filename).is_err() {
+        return Ok(edited);
+    }
+
+    let contents = fs::read_to_string(&filename)?;
+    for (i, line) in contents.lines().enumerate().skip(1) {
+        match line.split_once(',').and_then(|(pruid, date)| pruid.trim().parse::<isize>().ok().map(|p| (p, date.trim().to_string()))) {
+            Some(key) => {edited.insert(key);}
+            None => return Err(config.fill(Msg::EditsLineInvalid, &[&filename, &(i + 1)]).into()),
+        }
+    }
+    Ok(edited)
+}
+
+/// Save the keys of the rows edited or deleted locally, with a `pruid,date` header line.
+pub fn save_edits(edited: &BTreeSet<Key>, config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut s = String::from("pruid,date\n");
+    for (pruid, date) in edited.iter() {
+        s.push_str(&format!("{},{}\n", pruid, date));
+    }
+    fs::write(edits_file(config), s)?;
+    Ok(())
+}
+
+/// Name of the lock file next to the working file. Sessions lock it while they read or write the working
+/// file, so one session never reads a half-written file or writes over another session's save.
+pub fn lock_file(config: &Config) -> String {
+    format!("{}.lock", config.output)
+}
+
+/// What `lock_data` does before waiting for another session, set with `on_lock_wait`.
+static LOCK_WAIT: RwLock<Option<fn(&Config)>> = RwLock::new(None);
+
+/// Sets what to do when `lock_data` has to wait for another session to finish with the working file, such
+/// as telling the user with `Msg::LockWaiting`. Nothing is done by default, so the library never writes to
+/// the terminal itself.
+///
+/// # Examples
+///
+/// ```
+/// on_lock_wait(|config| eprintln!("{}", config.fill(Msg::LockWaiting, &[&config.output])));
+/// ```
+pub fn on_lock_wait(callback: fn(&Config)) {
+    *LOCK_WAIT.write().unwrap() = Some(callback);
+}
+
+/// Takes an advisory lock on the working file (exclusive for writing, shared for reading), waiting for
+/// other sessions to finish with it if needed. The lock is released when the returned file is dropped.
+///
+/// # Examples
+///
+/// ```
+/// let _lock = lock_data(&config, true)?;
+/// save_data(&column_labels, &data, &config)?;
+/// ```
+pub fn lock_data(config: &Config, exclusive: bool) -> Result<fs::File, Box<dyn Error>> {
+    let file = fs::OpenOptions::new().create(true).truncate(false).write(true).open(lock_file(config))?;
+    let taken = if exclusive { file.try_lock() } else { file.try_lock_shared() };
+    match taken {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => {
+            if let Some(callback) = *LOCK_WAIT.read().unwrap() {
+                callback(config);
+            }
+            if exclusive { file.lock()? } else { file.lock_shared()? }
+        }
+        Err(fs::TryLockError::Error(e)) => return Err(e.into()),
+    }
+    Ok(file)
+}
+
+/// What the working file looked like when it was last loaded or saved, to tell whether another session
+/// has saved it since.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stamp {
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+    pub hash: u64,
+}
+
+/// Takes the stamp of a file as it is now. There is none if the file doesn't exist.
+pub fn stamp(filename: &str) -> Result<Option<Stamp>, Box<dyn Error>> {
+    let contents = match fs::read(filename) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    let modified = fs::metadata(filename)?.modified().ok();
+    Ok(Some(Stamp {modified, len: contents.len() as u64, hash: hasher.finish()}))
+}
+
+/// Returns true if a file was changed (or created or removed) since its stamp was taken.
+///
+/// A file with the same modification time and size is taken as unchanged without being read, and a file
+/// that was only touched is unchanged as long as its contents are the same.
+pub fn changed_since(filename: &str, stamp: Option<&Stamp>) -> Result<bool, Box<dyn Error>> {
+    let metadata = match fs::metadata(filename) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(stamp.is_some()),
+        Err(e) => return Err(e.into()),
+    };
+    match stamp {
+        None => Ok(true),
+        Some(old) if old.modified.is_some() && metadata.modified().ok() == old.modified && metadata.len() == old.len => Ok(false),
+        Some(old) => Ok(self::stamp(filename)?.map(|s| s.hash) != Some(old.hash)),
+    }
+}
+
+/// Load a population table from a CSV file with a `pruid,population` header line.
+/// 
+/// The table replaces the built-in populations from the province reference table when computing
+/// per-capita rates, so any province left out of the file has no per-capita rates.
+pub fn load_population(filename: &str, config: &Config) -> Result<Population, Box<dyn Error>> {
+    let contents = fs::read_to_string(filename)?;
+    let mut by_pruid: HashMap<isize, u64> = HashMap::new();
+
+    for (i, line) in contents.lines().enumerate().skip(1) {
+        let fields: Vec<&str> = line.split(',').map(|e| e.trim()).collect();
+        if fields.len() != 2 {
+            return Err(config.fill(Msg::PopulationLineInvalid, &[&(i + 1)]).into());
+        }
+        by_pruid.insert(fields[0].parse()?, fields[1].parse()?);
+    }
+
+    Ok(Population::new(by_pruid))
+}
+
+/// Load the commands of a batch edit file (see `batch::parse`).
+pub fn load_batch(filename: &str, config: &Config) -> Result<Vec<(usize, Command)>, Box<dyn Error>> {
+    let contents = fs::read_to_string(filename)?;
+    Ok(batch::parse(&contents, &config.language)?)
+}
+
+/// Load the saved views and the view last shown from the `[views]` and `[display]` sections of a settings file.
+/// 
+/// Each setting of `[views]` is a view name followed by its columns, e.g. `compact = pruid, date, numtoday`,
+/// and `view` in `[display]` is the name of the view to start with.
+pub fn load_views(filename: &str, config: &Config) -> Result<Views, Box<dyn Error>> {
+    let ini = read_settings(filename, Some(&config.language))?;
+    let mut views = Views::default();
+
+    for (name, spec) in ini.section("views") {
+        match views::parse_columns(spec) {
+            Ok(columns) if views::valid_name(name) => {views.saved.insert(name.clone(), columns);}
+            Ok(_) => return Err(config.fill(Msg::ViewNameInvalid, &[name]).into()),
+            Err(column) => return Err(config.fill(Msg::SettingsViewInvalid, &[&filename, name, &column]).into()),
+        }
+    }
+    if let Some(name) = ini.get("display", "view") {
+        if views.select(name).is_err() || views.name.is_none() {
+            return Err(config.fill(Msg::SettingsViewMissing, &[&filename, &name]).into());
+        }
+    }
+
+    Ok(views)
+}
+
+/// Save the views to a settings file, keeping its comments and any other sections of the file as they were.
+pub fn save_views(filename: &str, views: &Views, config: &Config) -> Result<(), Box<dyn Error>> {
+    let mut ini = if fs::metadata(filename).is_ok() { read_settings(filename, Some(&config.language))? } else { Ini::default() };
+
+    let removed: Vec<String> = ini.section("views").map(|(name, _)| name.clone()).filter(|name| !views.saved.contains_key(name)).collect();
+    for name in removed {
+        ini.remove("views", &name);
+    }
+    for (name, columns) in views.saved.iter() {
+        ini.set("views", name, &views::column_spec(columns));
+    }
+    match &views.name {
+        Some(name) => ini.set("display", "view", name),
+        None => ini.remove("display", "view"),
+    }
+
+    fs::write(filename, ini.to_string())?;
+    Ok(())
+}
+
+/// Load the settings file if there is one, for building the `Config`.
+/// 
+/// The language isn't known yet when this is read, so errors are given in both languages.
+pub fn load_settings(filename: &str) -> Result<Option<Ini>, Box<dyn Error>> {
+    if fs::metadata(filename).is_err() {
+        return Ok(None);
+    }
+    Ok(Some(read_settings(filename, None)?))
+}
+
+/// Reads and parses a settings file, with errors in `lang` (or both languages if it is `None`).
+fn read_settings(filename: &str, lang: Option<&Lang>) -> Result<Ini, Box<dyn Error>> {
+    let contents = fs::read_to_string(filename)?;
+    Ini::parse(&contents).map_err(|line| match lang {
+        Some(lang) => messages::fill(lang, Msg::SettingsLineInvalid, &[&filename, &line]),
+        None => messages::both(Msg::SettingsLineInvalid, &[&filename, &line]),
+    }.into())
+}
+
 /// Takes a string in CSV format and returns an iterator without commas and removes undesired columns.
 fn default_filter(line: &str) -> impl Iterator<Item = &str> {
     line.split(",")
             .enumerate()
-            .filter_map(|(i, e)| if (i < 4) || (i >= 5 && i < 9) || (i == 13) || (i == 15) { Some(e) } else { None })
-}
-
-/// Returns a dynamic number of spaces according to an input integer.
-fn create_spacing(x: usize) -> String {
-    let x: isize = x.try_into().unwrap();
-    let mut i = 0;
-    let mut s = String::new();
-    while i < (x-2) {
-        s.push(' ');
-        i += 1;
-    }
-    s.push('\t');
-    s
+            .filter_map(|(i, e)| if (i < 4) || (5..9).contains(&i) || (i == 13) || (i == 15) { Some(e) } else { None })
 }
 
 /// Reassembles an iterator of strings (lines) into a newline-delimited String.
@@ -148,4 +487,200 @@ fn reassemble<'a>(it: impl Iterator<Item = &'a str>) -> String {
         line.push('\n');
     }
     String::from(line.trim())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+    use std::fs;
+
+    use crate::csv::{CsvFormat, Quote};
+    use crate::logic::{Config, Lang};
+    use crate::merge::Key;
+    use crate::persistence;
+    use crate::views::Views;
+
+    #[test]
+    fn test_views_file() {
+        let config = Config {language: Lang::EN, filename: String::from("covid19-download.csv"), ..Config::default()};
+        let filename = std::env::temp_dir().join(format!("tabledb-views-{}.ini", std::process::id()));
+        let filename = filename.to_str().unwrap();
+
+        //Other sections and comments of the settings file are kept when the views are saved
+        fs::write(filename, "; my settings\n[general]\nlanguage = fr\n").unwrap();
+        let mut views = Views::default();
+        views.select("date, numtoday, prname").unwrap();
+        views.save("daily");
+        persistence::save_views(filename, &views, &config).unwrap();
+
+        assert_eq!(persistence::load_views(filename, &config).unwrap(), views);
+        assert!(fs::read_to_string(filename).unwrap().starts_with("; my settings\n\n[general]\nlanguage = fr\n"));
+
+        fs::write(filename, "[views]\ndaily = date, nothing\n").unwrap();
+        assert!(persistence::load_views(filename, &config).is_err());
+        fs::write(filename, "[display]\nview = daily\n").unwrap();
+        assert!(persistence::load_views(filename, &config).is_err());
+        fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_views_file_case() {
+        let config = Config {language: Lang::EN, filename: String::from("covid19-download.csv"), ..Config::default()};
+        let filename = std::env::temp_dir().join(format!("tabledb-views-case-{}.ini", std::process::id()));
+        let filename = filename.to_str().unwrap();
+
+        //A view saved with capitals is found again after a restart, by its name in any case
+        let mut views = Views::default();
+        views.select("date, numtoday").unwrap();
+        views.save("Daily");
+        persistence::save_views(filename, &views, &config).unwrap();
+        let loaded = persistence::load_views(filename, &config).unwrap();
+        assert_eq!((loaded.name.as_deref(), &loaded.current), (Some("daily"), &views.current));
+
+        fs::write(filename, "[views]\nDaily = date, numtoday\n[display]\nview = DAILY\n").unwrap();
+        assert_eq!(persistence::load_views(filename, &config).unwrap(), loaded);
+        fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn test_edits_file() {
+        let output = std::env::temp_dir().join(format!("tabledb-edits-{}.csv", std::process::id()));
+        let config = Config {output: output.to_str().unwrap().to_string(), ..Config::default()};
+        assert!(persistence::load_edits(&config).unwrap().is_empty());
+
+        let edited: BTreeSet<Key> = vec![(35, String::from("2020-03-01")), (24, String::from("2020-03-02"))].into_iter().collect();
+        persistence::save_edits(&edited, &config).unwrap();
+        assert_eq!(persistence::load_edits(&config).unwrap(), edited);
+
+        fs::write(persistence::edits_file(&config), "pruid,date\nOntario\n").unwrap();
+        assert!(persistence::load_edits(&config).is_err());
+        fs::remove_file(persistence::edits_file(&config)).unwrap();
+    }
+
+    #[test]
+    fn test_stamp_and_lock() {
+        let output = std::env::temp_dir().join(format!("tabledb-lock-{}.csv", std::process::id()));
+        let config = Config {output: output.to_str().unwrap().to_string(), ..Config::default()};
+        assert_eq!(persistence::stamp(&config.output).unwrap(), None);
+        assert!(!persistence::changed_since(&config.output, None).unwrap());
+
+        fs::write(&config.output, "pruid,date\n35,2020-03-01\n").unwrap();
+        let stamp = persistence::stamp(&config.output).unwrap();
+        assert!(persistence::changed_since(&config.output, None).unwrap());
+        assert!(!persistence::changed_since(&config.output, stamp.as_ref()).unwrap());
+
+        //Rewriting the same contents isn't a change, but anything else is
+        fs::write(&config.output, "pruid,date\n35,2020-03-01\n").unwrap();
+        assert!(!persistence::changed_since(&config.output, stamp.as_ref()).unwrap());
+        fs::write(&config.output, "pruid,date\n35,2020-03-02\n").unwrap();
+        assert!(persistence::changed_since(&config.output, stamp.as_ref()).unwrap());
+
+        //A session writing the file keeps the others from locking it until it is done
+        let lock = persistence::lock_data(&config, true).unwrap();
+        let other = fs::File::open(persistence::lock_file(&config)).unwrap();
+        assert!(other.try_lock_shared().is_err());
+        drop(lock);
+        assert!(other.try_lock_shared().is_ok());
+
+        fs::remove_file(&config.output).unwrap();
+        fs::remove_file(persistence::lock_file(&config)).unwrap();
+    }
+
+    #[test]
+    fn test_load_constraints() {
+        let output = std::env::temp_dir().join(format!("tabledb-constraints-{}.csv", std::process::id()));
+        let config = Config {output: output.to_str().unwrap().to_string(), ..Config::default()};
+        let config = Config {filename: config.output.clone(), ..config};
+        let header = "pruid,prname,prname_fr,date,numconf,numprob,numdeaths,numtotal,numtoday,ratetotal\n";
+
+        //A correction makes the daily change negative, and the row still loads
+        fs::write(&config.output, format!("{}35,Ontario,Ontario,2020-03-02,9,0,0,9,-1,0.06\n", header)).unwrap();
+        assert_eq!(persistence::load_data(&config).unwrap().1[0].numtoday, -1);
+
+        //A pruid that isn't in the province reference table stops the load
+        fs::write(&config.output, format!("{}70,Ontario,Ontario,2020-03-02,9,0,0,9,1,0.06\n", header)).unwrap();
+        let error = persistence::load_data(&config).err().unwrap().to_string();
+        assert!(error.ends_with("line 2: pruid: \"70\" is not one of 1, 10, 11, 12, 13, 24, 35, 46, 47, 48, 59, 60, 61, 62, 99"), "{}", error);
+        fs::remove_file(&config.output).unwrap();
+    }
+
+    #[test]
+    fn test_working_file_as_input() {
+        let output = std::env::temp_dir().join(format!("tabledb-input-{}.csv", std::process::id()));
+        let config = Config {output: output.to_str().unwrap().to_string(), ..Config::default()};
+        let (column_labels, data) = persistence::load_data(&config).unwrap();
+        persistence::save_data(&column_labels, &data, &config).unwrap();
+
+        //A working file given with --input is still read by its columns
+        let config = Config {input: config.output.clone(), filename: config.output.clone(), output: String::from("other.csv"), ..Config::default()};
+        assert_eq!(persistence::load_data(&config).unwrap().1, data);
+        fs::remove_file(&config.input).unwrap();
+    }
+
+    #[test]
+    fn test_load_sql() {
+        let script = std::env::temp_dir().join(format!("tabledb-script-{}.sql", std::process::id()));
+        let config = Config::default();
+        let (_, data) = persistence::load_data(&config).unwrap();
+        persistence::save_sql(script.to_str().unwrap(), &data, &config).unwrap();
+        let mut config = config;
+        config.change_file(script.to_str().unwrap().to_string());
+        assert_eq!(persistence::load_data(&config).unwrap().1, data);
+
+        //Columns left out of an INSERT are empty, so a required one stops the load at the line of the values
+        fs::write(&script, "-- by hand\nINSERT INTO t (date, prname, numconf)\nVALUES ('2020-03-01', 'Ontario', 10);\n").unwrap();
+        let error = persistence::load_data(&config).err().unwrap().to_string();
+        assert!(error.ends_with("line 3: pruid: a value is required"), "{}", error);
+        fs::remove_file(&script).unwrap();
+    }
+
+    #[test]
+    fn test_load_arrow() {
+        let file = std::env::temp_dir().join(format!("tabledb-typed-{}.arrow", std::process::id()));
+        let config = Config::default();
+        let (_, data) = persistence::load_data(&config).unwrap();
+        persistence::save_arrow(file.to_str().unwrap(), &data, &config).unwrap();
+        let mut config = config;
+        config.change_file(file.to_str().unwrap().to_string());
+        assert_eq!(persistence::load_data(&config).unwrap().1, data);
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_csv_options() {
+        let output = std::env::temp_dir().join(format!("tabledb-excel-{}.csv", std::process::id()));
+        let mut config = Config {output: output.to_str().unwrap().to_string(), csv: CsvFormat::excel(), ..Config::default()};
+        let (column_labels, data) = persistence::load_data(&config).unwrap();
+        persistence::save_data(&column_labels, &data, &config).unwrap();
+        let contents = fs::read_to_string(&config.output).unwrap();
+        assert!(contents.starts_with("\u{feff}pruid;prname;prnameFR;date;") && contents.contains("\r\n"));
+        assert!(contents.contains(";Québec;") && contents.contains(";0,01\r\n"), "{}", contents);
+
+        //The delimiter, byte order mark and decimal commas are recognized whatever the options are
+        config.change_file(config.output.clone());
+        config.csv = CsvFormat::default();
+        let (labels, rows) = persistence::load_data(&config).unwrap();
+        assert_eq!((labels.labels, rows), (column_labels.labels, data));
+        fs::remove_file(&config.output).unwrap();
+    }
+
+    #[test]
+    fn test_csv_formats_round_trip() {
+        let output = std::env::temp_dir().join(format!("tabledb-formats-{}.csv", std::process::id()));
+        let config = Config {output: output.to_str().unwrap().to_string(), language: Lang::FR, ..Config::default()};
+        let (column_labels, data) = persistence::load_data(&config).unwrap();
+
+        //Every way of writing the working file reads back to the same rows, in French too
+        let formats = [CsvFormat::excel(), CsvFormat {quote: Quote::All, ..CsvFormat::excel()},
+                       CsvFormat {delimiter: '\t', quote: Quote::Text, ..CsvFormat::default()}, CsvFormat {decimal: ',', ..CsvFormat::default()}];
+        for csv in formats {
+            let config = Config {csv, ..config.clone()};
+            persistence::save_data(&column_labels, &data, &config).unwrap();
+            let mut config = Config {csv: CsvFormat::default(), ..config};
+            config.change_file(config.output.clone());
+            let (labels, rows) = persistence::load_data(&config).unwrap();
+            assert_eq!((labels.labels, rows), (column_labels.labels.clone(), data.clone()), "{:?}", csv);
+        }
+        fs::remove_file(&output).unwrap();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,126 @@
+//Final Project - Thomas Ivanov
+
+use std::sync::OnceLock;
+
+use crate::datastore::DataRow;
+
+/// A row of the built-in province reference table.
+///
+/// `population` is the Statistics Canada estimate for the fourth quarter of 2020, and is `None` for
+/// groupings that aren't a geographic area (repatriated travellers).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Province {
+    pub pruid: isize,
+    pub name: &'static str,
+    pub name_fr: &'static str,
+    pub abbr: &'static str,
+    pub population: Option<u64>,
+}
+
+/// Every province, territory and grouping that appears in the PHAC dataset, by `pruid`.
+pub const PROVINCES: [Province; 15] = [
+    Province {pruid: 1, name: "Canada", name_fr: "Canada", abbr: "CA", population: Some(38_008_005)},
+    Province {pruid: 10, name: "Newfoundland and Labrador", name_fr: "Terre-Neuve-et-Labrador", abbr: "NL", population: Some(520_553)},
+    Province {pruid: 11, name: "Prince Edward Island", name_fr: "Île-du-Prince-Édouard", abbr: "PE", population: Some(159_625)},
+    Province {pruid: 12, name: "Nova Scotia", name_fr: "Nouvelle-Écosse", abbr: "NS", population: Some(979_351)},
+    Province {pruid: 13, name: "New Brunswick", name_fr: "Nouveau-Brunswick", abbr: "NB", population: Some(781_476)},
+    Province {pruid: 24, name: "Quebec", name_fr: "Québec", abbr: "QC", population: Some(8_574_571)},
+    Province {pruid: 35, name: "Ontario", name_fr: "Ontario", abbr: "ON", population: Some(14_734_014)},
+    Province {pruid: 46, name: "Manitoba", name_fr: "Manitoba", abbr: "MB", population: Some(1_379_263)},
+    Province {pruid: 47, name: "Saskatchewan", name_fr: "Saskatchewan", abbr: "SK", population: Some(1_178_681)},
+    Province {pruid: 48, name: "Alberta", name_fr: "Alberta", abbr: "AB", population: Some(4_421_876)},
+    Province {pruid: 59, name: "British Columbia", name_fr: "Colombie-Britannique", abbr: "BC", population: Some(5_147_712)},
+    Province {pruid: 60, name: "Yukon", name_fr: "Yukon", abbr: "YT", population: Some(42_052)},
+    Province {pruid: 61, name: "Northwest Territories", name_fr: "Territoires du Nord-Ouest", abbr: "NT", population: Some(45_161)},
+    Province {pruid: 62, name: "Nunavut", name_fr: "Nunavut", abbr: "NU", population: Some(39_353)},
+    Province {pruid: 99, name: "Repatriated travellers", name_fr: "Voyageurs rapatriés", abbr: "RT", population: None},
+];
+
+/// Finds a province by its `pruid`.
+pub fn by_pruid(pruid: isize) -> Option<&'static Province> {
+    PROVINCES.iter().find(|p| p.pruid == pruid)
+}
+
+/// Finds a province by its abbreviation (e.g. "ON", "qc"), ignoring case.
+pub fn by_abbr(abbr: &str) -> Option<&'static Province> {
+    PROVINCES.iter().find(|p| p.abbr.eq_ignore_ascii_case(abbr.trim()))
+}
+
+/// Finds a province by its English or French name, ignoring case.
+pub fn by_name(name: &str) -> Option<&'static Province> {
+    let name = name.trim().to_lowercase();
+    PROVINCES.iter().find(|p| p.name.to_lowercase() == name || p.name_fr.to_lowercase() == name)
+}
+
+/// Every pruid of the table, for the allowed values of the `pruid` column. The list is built once and kept
+/// for the rest of the session, like the lists of names below.
+pub fn pruids() -> &'static [String] {
+    static PRUIDS: OnceLock<Vec<String>> = OnceLock::new();
+    PRUIDS.get_or_init(|| PROVINCES.iter().map(|p| p.pruid.to_string()).collect())
+}
+
+/// Every English name of the table, for the allowed values of the `prname` column.
+pub fn names() -> &'static [String] {
+    static NAMES: OnceLock<Vec<String>> = OnceLock::new();
+    NAMES.get_or_init(|| PROVINCES.iter().map(|p| p.name.to_string()).collect())
+}
+
+/// Every French name of the table, for the allowed values of the `prname_fr` column.
+pub fn names_fr() -> &'static [String] {
+    static NAMES_FR: OnceLock<Vec<String>> = OnceLock::new();
+    NAMES_FR.get_or_init(|| PROVINCES.iter().map(|p| p.name_fr.to_string()).collect())
+}
+
+/// Finds a province from any of the ways a user might write it: pruid, abbreviation, or name in either language.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(lookup("35"), lookup("ON"));
+/// assert_eq!(lookup("québec").unwrap().pruid, 24);
+/// ```
+pub fn lookup(s: &str) -> Option<&'static Province> {
+    match s.trim().parse::<isize>() {
+        Ok(pruid) => by_pruid(pruid),
+        Err(_) => by_abbr(s).or_else(|| by_name(s)),
+    }
+}
+
+/// Sets the names of a data row to the reference names for its `pruid`, returning true if anything changed.
+/// Rows with a `pruid` that isn't in the table are left as they are.
+pub fn normalize(row: &mut DataRow) -> bool {
+    match by_pruid(row.pruid) {
+        Some(p) if row.prname != p.name || row.prname_fr != p.name_fr => {
+            row.prname = String::from(p.name);
+            row.prname_fr = String::from(p.name_fr);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Makes a data row refer to another province, setting its `pruid` and both names from the table.
+pub fn assign(row: &mut DataRow, province: &Province) {
+    row.pruid = province.pruid;
+    row.prname = String::from(province.name);
+    row.prname_fr = String::from(province.name_fr);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::datastore::DataRow;
+    use crate::province;
+
+    #[test]
+    fn test_lookup_and_normalize() {
+        assert_eq!(province::lookup("35"), province::lookup("on"));
+        assert_eq!(province::lookup("Québec").unwrap().abbr, "QC");
+        assert_eq!(province::lookup("colombie-britannique").unwrap().pruid, 59);
+        assert_eq!(province::lookup("XX"), None);
+
+        let mut row = DataRow::new(vec!["24", "Quebec", "Quebec", "2020-03-01", "1", "0", "0", "1", "1", "0.0"]);
+        assert!(province::normalize(&mut row));
+        assert_eq!(row.prname_fr, "Québec");
+        assert!(!province::normalize(&mut row));
+    }
+}
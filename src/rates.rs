@@ -0,0 +1,172 @@
+//Final Project - Thomas Ivanov
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use chrono::{Duration, NaiveDate};
+
+use crate::datastore::DataRow;
+use crate::locale;
+use crate::logic::Lang;
+use crate::province;
+
+/// Labels of the computed columns, in display order. They are numbered 10-13 for sorting, after the stored columns.
+pub const RATE_COLUMNS: [&str; 4] = ["cases100k", "deaths100k", "cfr", "active"];
+
+/// Population by `pruid`, used to normalize counts into per-capita rates.
+///
+/// The default table comes from the province reference table, and can be replaced by a user-supplied
+/// one (see `persistence::load_population`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Population {
+    by_pruid: HashMap<isize, u64>,
+}
+
+impl Population {
+    /// A function for creating a new `Population` table from a map of pruid to population.
+    pub fn new(by_pruid: HashMap<isize, u64>) -> Population {
+        Population {
+            by_pruid
+        }
+    }
+
+    /// Returns the population of a province, or `None` if it isn't in the table.
+    pub fn get(&self, pruid: isize) -> Option<u64> {
+        self.by_pruid.get(&pruid).copied().filter(|p| *p > 0)
+    }
+}
+
+impl Default for Population {
+    fn default() -> Population {
+        Population::new(province::PROVINCES.iter()
+                            .filter_map(|p| p.population.map(|pop| (p.pruid, pop)))
+                            .collect())
+    }
+}
+
+/// Computes the derived columns of data rows: cases and deaths per 100,000 people, case fatality
+/// ratio (`numdeaths / numtotal`, as a percentage) and an estimate of active cases.
+///
+/// Active cases are estimated as the cases reported in the last 14 days, i.e. `numtotal` minus the
+/// `numtotal` of the same province on the latest date at least 14 days earlier. This needs the other
+/// rows of the province, so a `Rates` is built over the whole table before it is used.
+///
+/// # Examples
+///
+/// ```
+/// let rates = Rates::new(&data, &Population::default());
+/// let cfr = rates.value(&data[0], "cfr");
+/// ```
+pub struct Rates<'a> {
+    population: &'a Population,
+    totals: HashMap<isize, Vec<(NaiveDate, isize)>>,
+}
+
+impl<'a> Rates<'a> {
+    /// A function for creating a new `Rates` over a table of data rows.
+    pub fn new(data: &[DataRow], population: &'a Population) -> Rates<'a> {
+        let mut totals: HashMap<isize, Vec<(NaiveDate, isize)>> = HashMap::new();
+        for row in data.iter() {
+            if let Ok(date) = NaiveDate::parse_from_str(&row.date, "%Y-%m-%d") {
+                totals.entry(row.pruid).or_default().push((date, row.numtotal));
+            }
+        }
+        for list in totals.values_mut() {
+            list.sort();
+        }
+        Rates {
+            population,
+            totals,
+        }
+    }
+
+    /// Returns the value of a computed column for a row, or `None` if the column doesn't exist or
+    /// can't be computed (unknown population, no cases for the fatality ratio, or an invalid date).
+    pub fn value(&self, row: &DataRow, column: &str) -> Option<f64> {
+        match column {
+            "cases100k" => self.population.get(row.pruid).map(|p| row.numtotal as f64 * 100_000.0 / p as f64),
+            "deaths100k" => self.population.get(row.pruid).map(|p| row.numdeaths as f64 * 100_000.0 / p as f64),
+            "cfr" => if row.numtotal != 0 { Some(row.numdeaths as f64 * 100.0 / row.numtotal as f64) } else { None },
+            "active" => {
+                let date = NaiveDate::parse_from_str(&row.date, "%Y-%m-%d").ok()?;
+                let cutoff = date - Duration::days(14);
+                let list = self.totals.get(&row.pruid)?;
+                //The latest total on or before the cutoff date (none means every case is recent)
+                let before = match list.partition_point(|(d, _)| *d <= cutoff) {
+                    0 => 0,
+                    i => list[i - 1].1,
+                };
+                Some((row.numtotal - before) as f64)
+            }
+            _ => None,
+        }
+    }
+
+    /// Produces the cells of the computed columns of a row, to follow its stored columns.
+    /// Rates are formatted for the language with two decimals, and active cases as a whole number.
+    pub fn cells(&self, row: &DataRow, lang: &Lang) -> Vec<String> {
+        RATE_COLUMNS.iter()
+                    .map(|column| match self.value(row, column) {
+                        Some(v) => locale::format_float(v, if *column == "active" { 0 } else { 2 }, lang),
+                        None => String::from("-"),
+                    })
+                    .collect()
+    }
+
+    /// Sorts rows by the computed column numbered `sorting` (10-13), in reverse if `rev` is set.
+    /// Returns false without sorting if `sorting` isn't the number of a computed column.
+    pub fn sort<T: Borrow<DataRow>>(&self, data: &mut [T], sorting: &str, rev: bool) -> bool {
+        let column = match column_for_key(sorting) {
+            Some(column) => column,
+            None => return false,
+        };
+        data.sort_by(|a, b| {
+            let order = self.value(a.borrow(), column).partial_cmp(&self.value(b.borrow(), column)).unwrap_or(Ordering::Equal);
+            if rev { order.reverse() } else { order }
+        });
+        true
+    }
+}
+
+/// Returns the computed column for a sorting number (10-13), if it is one.
+pub fn column_for_key(sorting: &str) -> Option<&'static str> {
+    match sorting.trim().parse::<usize>() {
+        Ok(n) if (10..10 + RATE_COLUMNS.len()).contains(&n) => Some(RATE_COLUMNS[n - 10]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::datastore::DataRow;
+    use crate::logic::Lang;
+    use crate::rates::{Population, Rates};
+
+    #[test]
+    fn test_rates() {
+        let population = Population::new(vec![(35, 1_000_000)].into_iter().collect::<HashMap<isize, u64>>());
+        let data = vec![
+            DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "100", "0", "2", "100", "100", "0.1"]),
+            DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-15", "150", "0", "3", "150", "50", "0.1"]),
+            DataRow::new(vec!["99", "Repatriated travellers", "Voyageurs rapatriés", "2020-03-15", "0", "0", "0", "0", "0", "0.0"]),
+        ];
+        let rates = Rates::new(&data, &population);
+
+        assert_eq!(rates.value(&data[1], "cases100k"), Some(15.0));
+        assert_eq!(rates.value(&data[1], "deaths100k"), Some(0.3));
+        assert_eq!(rates.value(&data[1], "cfr"), Some(2.0));
+        assert_eq!(rates.value(&data[0], "active"), Some(100.0));
+        assert_eq!(rates.value(&data[1], "active"), Some(50.0));
+        assert_eq!(rates.value(&data[2], "cases100k"), None);
+        assert_eq!(rates.value(&data[2], "cfr"), None);
+        assert_eq!(rates.cells(&data[1], &Lang::FR), vec!["15,00", "0,30", "2,00", "50"]);
+        assert_eq!(rates.cells(&data[2], &Lang::EN)[0], "-");
+
+        let mut sorted: Vec<&DataRow> = data.iter().collect();
+        assert!(rates.sort(&mut sorted, "13", true));
+        assert_eq!(sorted[0], &data[0]);
+        assert!(!rates.sort(&mut sorted, "9", false));
+    }
+}
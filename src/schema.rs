@@ -0,0 +1,252 @@
+//Final Project - Thomas Ivanov
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+use chrono::NaiveDate;
+use regex::Regex;
+
+use crate::datastore::DataRow;
+use crate::logic::Lang;
+use crate::messages::{self, Msg};
+use crate::province;
+
+/// The type of the values of a column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Integer,
+    Decimal,
+    Text,
+    Date,
+}
+
+/// The constraints on the values of a stored column. Constraints that are `None` aren't checked.
+#[derive(Clone, Copy, Debug)]
+pub struct Column {
+    pub name: &'static str,
+    pub kind: Kind,
+    /// Whether an empty value is refused. Empty counts and rates are read as 0.
+    pub required: bool,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// The most characters a value can have.
+    pub max_len: Option<usize>,
+    /// The most digits a value can have after the decimal point.
+    pub precision: Option<usize>,
+    /// A regular expression the whole value must match.
+    pub pattern: Option<&'static str>,
+    /// The only values allowed, for columns with a fixed set of them.
+    pub allowed: Option<fn() -> &'static [String]>,
+}
+
+const TEXT: Column = Column {name: "", kind: Kind::Text, required: true, min: None, max: None, max_len: None, precision: None, pattern: None, allowed: None};
+const COUNT: Column = Column {kind: Kind::Integer, min: Some(0.0), max: Some(99999999.0), ..TEXT};
+
+/// The constraints on every stored column, in the order of `DataRow::public_vec`.
+///
+/// They are checked when a file is loaded or saved and whenever a value is typed in, inserted or set by
+/// a batch edit file, so this is the one place to change what the table accepts. A `pruid` must be one of
+/// the province reference table, so a file with a row for any other area isn't loaded, and `numtoday` can be
+/// negative, unlike the other counts.
+pub const SCHEMA: [Column; 10] = [
+    Column {name: "pruid", max: Some(999.0), allowed: Some(province::pruids), ..COUNT},
+    Column {name: "prname", max_len: Some(30), allowed: Some(province::names), ..TEXT},
+    Column {name: "prname_fr", max_len: Some(30), allowed: Some(province::names_fr), ..TEXT},
+    Column {name: "date", kind: Kind::Date, pattern: Some(r"^\d{4}-\d{2}-\d{2}$"), ..TEXT},
+    Column {name: "numconf", ..COUNT},
+    Column {name: "numprob", ..COUNT},
+    Column {name: "numdeaths", required: false, ..COUNT},
+    Column {name: "numtotal", ..COUNT},
+    //The daily change goes down when earlier counts are corrected
+    Column {name: "numtoday", min: Some(-99999999.0), ..COUNT},
+    Column {name: "ratetotal", kind: Kind::Decimal, required: false, max: Some(99999.99), precision: Some(2), ..COUNT},
+];
+
+/// The constraint broken by a value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Broken {
+    Missing,
+    NotInteger,
+    NotNumber,
+    NotDate,
+    TooSmall(f64),
+    TooLarge(f64),
+    TooLong(usize),
+    Precision(usize),
+    Pattern(&'static str),
+    NotAllowed(String),
+}
+
+/// A value that breaks a constraint of its column, described with the column's name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnError {
+    pub column: &'static str,
+    pub value: String,
+    pub broken: Broken,
+}
+
+impl ColumnError {
+    /// Describes the error in a language, e.g. "numconf: 123456789 is more than the maximum of 99999999".
+    pub fn describe(&self, lang: &Lang) -> String {
+        let (column, value) = (&self.column, &self.value);
+        match &self.broken {
+            Broken::Missing => messages::fill(lang, Msg::SchemaMissing, &[column]),
+            Broken::NotInteger => messages::fill(lang, Msg::SchemaNotInteger, &[column, value]),
+            Broken::NotNumber => messages::fill(lang, Msg::SchemaNotNumber, &[column, value]),
+            Broken::NotDate => messages::fill(lang, Msg::SchemaNotDate, &[column, value]),
+            Broken::TooSmall(min) => messages::fill(lang, Msg::SchemaTooSmall, &[column, value, min]),
+            Broken::TooLarge(max) => messages::fill(lang, Msg::SchemaTooLarge, &[column, value, max]),
+            Broken::TooLong(len) => messages::fill(lang, Msg::SchemaTooLong, &[column, value, len]),
+            Broken::Precision(digits) => messages::fill(lang, Msg::SchemaPrecision, &[column, value, digits]),
+            Broken::Pattern(pattern) => messages::fill(lang, Msg::SchemaPattern, &[column, value, pattern]),
+            Broken::NotAllowed(allowed) => messages::fill(lang, Msg::SchemaNotAllowed, &[column, value, allowed]),
+        }
+    }
+}
+
+impl fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.describe(&Lang::EN))
+    }
+}
+
+impl Error for ColumnError {}
+
+impl Column {
+    /// Checks a value against every constraint of the column.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// assert!(SCHEMA[4].check("412").is_ok());
+    /// assert_eq!(SCHEMA[9].check("1.234").unwrap_err().broken, Broken::Precision(2));
+    /// ```
+    pub fn check(&self, value: &str) -> Result<(), ColumnError> {
+        self.check_format(value)?;
+        let value = value.trim();
+        match self.allowed {
+            Some(allowed) if !value.is_empty() && !allowed().iter().any(|a| a == value) => Err(self.error(value, Broken::NotAllowed(allowed().join(", ")))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks a value against every constraint of the column but its allowed values.
+    pub fn check_format(&self, value: &str) -> Result<(), ColumnError> {
+        let value = value.trim();
+        if value.is_empty() {
+            return if self.required { Err(self.error(value, Broken::Missing)) } else { Ok(()) };
+        }
+        if let Some(len) = self.max_len {
+            if value.chars().count() > len {
+                return Err(self.error(value, Broken::TooLong(len)));
+            }
+        }
+        if let Some(pattern) = self.pattern {
+            if !matches(pattern, value) {
+                return Err(self.error(value, Broken::Pattern(pattern)));
+            }
+        }
+
+        let number = match self.kind {
+            Kind::Integer => Some(value.parse::<i64>().map_err(|_| self.error(value, Broken::NotInteger))? as f64),
+            Kind::Decimal => match value.parse::<f64>() {
+                Ok(n) if n.is_finite() => Some(n),
+                _ => return Err(self.error(value, Broken::NotNumber)),
+            },
+            Kind::Date => {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| self.error(value, Broken::NotDate))?;
+                None
+            }
+            Kind::Text => None,
+        };
+        if let Some(number) = number {
+            if let Some(min) = self.min.filter(|min| number < *min) {
+                return Err(self.error(value, Broken::TooSmall(min)));
+            }
+            if let Some(max) = self.max.filter(|max| number > *max) {
+                return Err(self.error(value, Broken::TooLarge(max)));
+            }
+        }
+        if let Some(digits) = self.precision {
+            if value.split_once('.').map_or(0, |(_, decimals)| decimals.len()) > digits {
+                return Err(self.error(value, Broken::Precision(digits)));
+            }
+        }
+        Ok(())
+    }
+
+    fn error(&self, value: &str, broken: Broken) -> ColumnError {
+        ColumnError {column: self.name, value: value.to_string(), broken}
+    }
+}
+
+/// Returns true if a value matches a pattern. Patterns are compiled once and kept for the rest of the session.
+fn matches(pattern: &'static str, value: &str) -> bool {
+    static COMPILED: OnceLock<Mutex<HashMap<&'static str, Regex>>> = OnceLock::new();
+    let mut compiled = COMPILED.get_or_init(|| Mutex::new(HashMap::new())).lock().unwrap();
+    compiled.entry(pattern).or_insert_with(|| Regex::new(pattern).expect("invalid pattern in schema")).is_match(value)
+}
+
+/// Checks the text values of a stored row (one per column of `SCHEMA`, in order) against every constraint
+/// but the allowed values, so they can be read into a `DataRow`. Returns the first problem found.
+pub fn check_fields(fields: &[&str]) -> Result<(), ColumnError> {
+    SCHEMA.iter().zip(fields.iter()).try_for_each(|(column, value)| column.check_format(value))
+}
+
+/// Checks every value of a data row against every constraint. Returns the first problem found.
+pub fn check_row(row: &DataRow) -> Result<(), ColumnError> {
+    SCHEMA.iter().zip(row.public_vec().iter()).try_for_each(|(column, value)| column.check(value))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::datastore::DataRow;
+    use crate::logic::Lang;
+    use crate::province;
+    use crate::schema::{self, Broken, SCHEMA};
+
+    fn broken(column: usize, value: &str) -> Option<Broken> {
+        SCHEMA[column].check(value).err().map(|e| e.broken)
+    }
+
+    #[test]
+    fn test_check_text() {
+        assert_eq!(broken(0, "35"), None);
+        assert_eq!(broken(0, "36"), Some(Broken::NotAllowed(province::pruids().join(", "))));
+        assert_eq!(broken(1, "Quebec"), None);
+        assert_eq!(broken(2, &"x".repeat(31)), Some(Broken::TooLong(30)));
+        assert_eq!(broken(3, "2020-3-1"), Some(Broken::Pattern(r"^\d{4}-\d{2}-\d{2}$")));
+        assert_eq!(broken(3, "2020-02-30"), Some(Broken::NotDate));
+    }
+
+    #[test]
+    fn test_check_numbers() {
+        assert_eq!(broken(0, "1000"), Some(Broken::TooLarge(999.0)));
+        assert_eq!(broken(4, ""), Some(Broken::Missing));
+        assert_eq!(broken(6, ""), None);
+        assert_eq!(broken(5, "-1"), Some(Broken::TooSmall(0.0)));
+        assert_eq!(broken(8, "-4"), None);
+        assert_eq!(broken(7, "1.5"), Some(Broken::NotInteger));
+        assert_eq!(broken(9, "NaN"), Some(Broken::NotNumber));
+        assert_eq!(broken(9, "1.234"), Some(Broken::Precision(2)));
+    }
+
+    #[test]
+    fn test_describe() {
+        let error = SCHEMA[4].check("123456789").unwrap_err();
+        assert_eq!(error.describe(&Lang::EN), "numconf: 123456789 is more than the maximum of 99999999");
+        assert_eq!(error.describe(&Lang::FR), "numconf : 123456789 dépasse le maximum de 99999999");
+    }
+
+    #[test]
+    fn test_check_row() {
+        //Fields are checked for their format, and rows for their allowed values too
+        assert!(schema::check_fields(&["24", "Quebec", "Quebec", "2020-03-01", "1", "0", "", "1", "1", ""]).is_ok());
+        let row = DataRow::new(vec!["24", "Quebec", "Quebec", "2020-03-01", "1", "0", "", "1", "1", ""]);
+        assert_eq!(schema::check_row(&row).unwrap_err().column, "prname_fr");
+        let row = DataRow::new(vec!["24", "Quebec", "Québec", "2020-03-01", "1", "0", "", "1", "1", ""]);
+        assert!(schema::check_row(&row).is_ok());
+    }
+}
@@ -0,0 +1,511 @@
+//Final Project - Thomas Ivanov
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+
+use serde_json::{json, Map, Value};
+use tiny_http::{Response, Server};
+
+use crate::datastore::{DataRow, Header};
+use crate::index::Index;
+use crate::logic::{self, Config};
+use crate::persistence::Stamp;
+use crate::merge::{self, Key};
+use crate::messages::Msg;
+use crate::ops::{self, EditError};
+use crate::rates::{self, Population, Rates};
+use crate::views;
+
+/// The answer to a request: an HTTP status code and a JSON body.
+pub type Reply = (u16, Value);
+
+/// The loaded table served by `serve`, with everything needed to query, change and save it.
+///
+/// Every request goes through the same functions as the menu: `logic::query` and `logic::sort` for
+/// reading, the `ops` functions for changes, and `logic::save` to write each change to the working file.
+pub struct Table<'a> {
+    pub config: &'a Config,
+    pub column_labels: &'a Header,
+    pub population: &'a Population,
+    pub data: &'a mut Vec<DataRow>,
+    pub index: &'a mut Index,
+    pub edited: &'a mut BTreeSet<Key>,
+    /// The stamp of the working file when it was last loaded or saved.
+    pub stamp: &'a mut Option<Stamp>,
+    /// Whether each change is saved as soon as it is made.
+    pub persist: bool,
+}
+
+/// The table as it was before a change, put back if the change can't be saved.
+struct Snapshot {
+    data: Vec<DataRow>,
+    index: Index,
+    edited: BTreeSet<Key>,
+    stamp: Option<Stamp>,
+}
+
+/// Serves the table over HTTP on a local address (such as `127.0.0.1:8080`) until the process is stopped.
+///
+/// # Examples
+///
+/// ```
+/// my_project> cargo run -- en --serve 8080
+/// my_project> curl "http://127.0.0.1:8080/rows?filter=abbr=ON&sort=-date&page_size=10"
+/// ```
+pub fn serve(address: &str, table: &mut Table) -> Result<(), Box<dyn Error>> {
+    let server = Server::http(address).map_err(|e| e.to_string())?;
+    println!("{}", table.config.fill(Msg::ServeListening, &[&table.data.len(), &address]));
+
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let (status, value) = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => table.handle(request.method().as_str(), request.url(), &body),
+            Err(_) => table.error(400, Msg::ServeBodyInvalid, &[]),
+        };
+        let response = Response::from_string(value.to_string())
+                                .with_status_code(status)
+                                .with_header("Content-Type: application/json; charset=utf-8".parse::<tiny_http::Header>().unwrap());
+        //A client that went away doesn't stop the server
+        if let Err(e) = request.respond(response) {
+            eprintln!("{}", e);
+        }
+    }
+    Ok(())
+}
+
+impl Table<'_> {
+    /// Answers a request given its method, URL (path and query string) and body.
+    ///
+    /// * `GET /rows` lists rows, with optional `filter` (conditions or a saved query, as in option 3), `sort`
+    ///   (columns, with `-` in front for descending), `page` and `page_size` parameters
+    /// * `GET /rows/{pruid}/{date}` returns a row, `POST /rows` adds one, `PATCH /rows/{pruid}/{date}` changes
+    ///   some of its columns and `DELETE /rows/{pruid}/{date}` deletes it. Bodies are JSON objects of column
+    ///   values, so a row returned by `GET` can be sent back changed: its `id` is ignored. Rows are found by their key rather than their position, so the id of a row (`35/2020-03-01`)
+    ///   stays the same when other rows are inserted or deleted, and only changes with its pruid or date.
+    /// * `GET /aggregate` computes `fn` (`sum`, `avg`, `min`, `max` or `count`) of a numeric `column`,
+    ///   grouped `by` `pruid`, `date` or nothing, over the rows matching an optional `filter`
+    pub fn handle(&mut self, method: &str, url: &str, body: &str) -> Reply {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let params: BTreeMap<String, String> = query.split('&')
+                                                    .filter(|p| !p.is_empty())
+                                                    .map(|p| { let (k, v) = p.split_once('=').unwrap_or((p, "")); (decode(k), decode(v)) })
+                                                    .collect();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        match (method, segments.as_slice()) {
+            ("GET", ["rows"]) => self.list(&params),
+            ("GET", ["rows", pruid, date]) => self.row_at(pruid, date).map_or_else(|e| e, |pos| (200, row_json(&self.data[pos]))),
+            ("POST", ["rows"]) => self.insert(body),
+            ("PATCH", ["rows", pruid, date]) => self.update(pruid, date, body),
+            ("DELETE", ["rows", pruid, date]) => self.delete(pruid, date),
+            ("GET", ["aggregate"]) => self.aggregate(&params),
+            _ => self.error(404, Msg::ServeNotFound, &[&method, &path]),
+        }
+    }
+
+    fn error(&self, status: u16, msg: Msg, args: &[&dyn std::fmt::Display]) -> Reply {
+        (status, json!({"error": self.config.fill(msg, args)}))
+    }
+
+    /// The rows matching a `filter` parameter, or every row.
+    fn filtered(&self, params: &BTreeMap<String, String>) -> Result<Vec<usize>, Reply> {
+        let filter = match params.get("filter") {
+            Some(filter) => self.config.query(filter).unwrap_or(filter),
+            None => return Ok((0..self.data.len()).collect()),
+        };
+        let rates = Rates::new(self.data, self.population);
+        logic::query(filter.clone(), self.config, self.data, self.index, &rates).map_err(|e| (400, json!({"error": e.to_string()})))
+    }
+
+    fn list(&self, params: &BTreeMap<String, String>) -> Reply {
+        let ids = match self.filtered(params) {
+            Ok(ids) => ids,
+            Err(reply) => return reply,
+        };
+        //Pages are numbered from 1, and hold 1-1000 rows like in the menu
+        let page = match params.get("page").map(|p| (p, p.parse::<usize>())) {
+            None => 1,
+            Some((_, Ok(page))) if page > 0 => page,
+            Some((value, _)) => return self.error(400, Msg::ServeParamInvalid, &[value, &"page"]),
+        };
+        let page_size = match params.get("page_size").map(|p| (p, p.parse::<usize>())) {
+            None => self.config.page_size,
+            Some((_, Ok(size))) if (1..=1000).contains(&size) => size,
+            Some((value, _)) => return self.error(400, Msg::ServeParamInvalid, &[value, &"page_size"]),
+        };
+
+        let mut entries: Vec<&DataRow> = ids.into_iter().map(|id| &self.data[id]).collect();
+        if let Some(spec) = params.get("sort") {
+            let rates = Rates::new(self.data, self.population);
+            //The columns are sorted by from the lowest priority up, since each sort keeps the order of equal rows
+            for column in spec.split(',').map(|c| c.trim()).rev() {
+                let (descending, name) = match column.strip_prefix('-') {
+                    Some(name) => (true, name),
+                    None => (false, column),
+                };
+                let sorting = match views::column_index(name) {
+                    Some(c) => (c + 1).to_string(),
+                    None => return self.error(400, Msg::ServeParamInvalid, &[&column, &"sort"]),
+                };
+                //Descending sorts keep equal rows in order by sorting the reversed rows and reversing them back
+                if descending {
+                    entries.reverse();
+                }
+                if rates::column_for_key(&sorting).is_some() {
+                    rates.sort(&mut entries, &sorting, false);
+                } else {
+                    logic::sort(&mut entries, &sorting, self.config).ok();
+                }
+                if descending {
+                    entries.reverse();
+                }
+            }
+        }
+
+        let total = entries.len();
+        let pages = total.div_ceil(page_size).max(1);
+        //A page past the last one is empty, however large its number
+        let rows: Vec<Value> = entries.iter().skip((page - 1).saturating_mul(page_size)).take(page_size).map(|row| row_json(row)).collect();
+        (200, json!({"total": total, "page": page, "page_size": page_size, "pages": pages, "rows": rows}))
+    }
+
+    /// Finds the position of a row from the pruid and date of its id in the path.
+    fn row_at(&self, pruid: &str, date: &str) -> Result<usize, Reply> {
+        let date = decode(date);
+        match pruid.parse::<isize>().ok().and_then(|pruid| ops::find_row(self.index, &(pruid, date.clone()))) {
+            Some(pos) => Ok(pos),
+            None => Err(self.error(404, Msg::ServeRowNotFound, &[&format!("{}/{}", pruid, date)])),
+        }
+    }
+
+    /// Reads a JSON object of column values from a request body, leaving out the `id` that `row_json` adds.
+    fn fields(&self, body: &str) -> Result<Vec<(String, String)>, Reply> {
+        let object: Map<String, Value> = serde_json::from_str(body).map_err(|_| self.error(400, Msg::ServeBodyInvalid, &[]))?;
+        Ok(object.into_iter().filter(|(column, _)| column != "id").map(|(column, value)| {
+            let value = match value {
+                Value::String(s) => s,
+                Value::Null => String::new(),
+                other => other.to_string(),
+            };
+            (column, value)
+        }).collect())
+    }
+
+    /// Takes a snapshot of the table before a change, if the table is persisted (see `save`).
+    fn snapshot(&self) -> Option<Snapshot> {
+        self.persist.then(|| Snapshot {data: self.data.clone(), index: self.index.clone(), edited: self.edited.clone(), stamp: *self.stamp})
+    }
+
+    /// Saves the table after a change, if the table is persisted, merging in whatever another session saved.
+    /// If the save fails, the table is put back as it was `before` the change, so the change isn't written by
+    /// a later save after the client was told it failed.
+    fn save(&mut self, before: Option<Snapshot>) -> Result<(), Reply> {
+        if let Some(before) = before {
+            if let Err(e) = logic::save_merged(self.column_labels, self.data, self.index, self.edited, self.config, self.stamp) {
+                *self.data = before.data;
+                *self.index = before.index;
+                *self.edited = before.edited;
+                *self.stamp = before.stamp;
+                return Err((500, json!({"error": e.to_string()})));
+            }
+        }
+        Ok(())
+    }
+
+    fn refused(&self, e: EditError) -> Reply {
+        (400, json!({"error": e.describe(&self.config.language)}))
+    }
+
+    fn insert(&mut self, body: &str) -> Reply {
+        let fields = match self.fields(body) {
+            Ok(fields) => fields,
+            Err(reply) => return reply,
+        };
+        let row = match ops::new_row(&fields) {
+            Ok(row) => row,
+            Err(e) => return self.refused(e),
+        };
+        if ops::find_row(self.index, &merge::key(&row)).is_some() {
+            return self.error(409, Msg::ServeRowExists, &[&row.pruid, &row.date]);
+        }
+        let before = self.snapshot();
+        ops::insert_row(self.data, self.index, self.edited, row);
+        let pos = self.data.len() - 1;
+        self.save(before).map(|_| (201, row_json(&self.data[pos]))).unwrap_or_else(|e| e)
+    }
+
+    fn update(&mut self, pruid: &str, date: &str, body: &str) -> Reply {
+        let (id, fields) = match (self.row_at(pruid, date), self.fields(body)) {
+            (Ok(id), Ok(fields)) => (id, fields),
+            (Err(reply), _) | (_, Err(reply)) => return reply,
+        };
+        //Every value is checked on a copy of the row first, so a refused value changes nothing
+        let mut row = self.data[id].clone();
+        if let Some(e) = fields.iter().find_map(|(column, value)| ops::set_field(&mut row, column, value).err()) {
+            return self.refused(e);
+        }
+        let key = merge::key(&row);
+        if key != merge::key(&self.data[id]) && ops::find_row(self.index, &key).is_some() {
+            return self.error(409, Msg::ServeRowExists, &[&key.0, &key.1]);
+        }
+        let before = self.snapshot();
+        for (column, value) in fields.iter() {
+            if let Err(e) = ops::edit_row(self.data, self.index, self.edited, id, column, value) {
+                return self.refused(e);
+            }
+        }
+        self.save(before).map(|_| (200, row_json(&self.data[id]))).unwrap_or_else(|e| e)
+    }
+
+    fn delete(&mut self, pruid: &str, date: &str) -> Reply {
+        let id = match self.row_at(pruid, date) {
+            Ok(id) => id,
+            Err(reply) => return reply,
+        };
+        let before = self.snapshot();
+        let removed = match ops::delete_rows(self.data, self.index, self.edited, &[id]) {
+            Ok(removed) => removed,
+            Err(e) => return self.refused(e),
+        };
+        self.save(before).map(|_| (200, row_json(&removed[0]))).unwrap_or_else(|e| e)
+    }
+
+    fn aggregate(&self, params: &BTreeMap<String, String>) -> Reply {
+        let ids = match self.filtered(params) {
+            Ok(ids) => ids,
+            Err(reply) => return reply,
+        };
+        let column = params.get("column").map_or("numtoday", |c| c.as_str());
+        let function = params.get("fn").map_or("sum", |f| f.as_str());
+        let by = params.get("by").map_or("none", |b| b.as_str());
+        if !["sum", "avg", "min", "max", "count"].contains(&function) {
+            return self.error(400, Msg::ServeParamInvalid, &[&function, &"fn"]);
+        }
+        if !["pruid", "date", "none"].contains(&by) {
+            return self.error(400, Msg::ServeParamInvalid, &[&by, &"by"]);
+        }
+        let rates = Rates::new(self.data, self.population);
+        if self.data.first().is_some_and(|row| row.numeric(column).is_none()) && !rates::RATE_COLUMNS.contains(&column) {
+            return self.error(400, Msg::ServeParamInvalid, &[&column, &"column"]);
+        }
+
+        //Rows whose value can't be computed (such as a rate with no population) are left out
+        let mut groups: BTreeMap<(isize, String), Vec<f64>> = BTreeMap::new();
+        for id in ids {
+            let row = &self.data[id];
+            let group = match by {
+                "pruid" => (row.pruid, String::new()),
+                "date" => (0, row.date.clone()),
+                _ => (0, String::new()),
+            };
+            let values = groups.entry(group).or_default();
+            if let Some(v) = row.numeric(column).or_else(|| rates.value(row, column)) {
+                values.push(v);
+            }
+        }
+        let groups: Vec<Value> = groups.into_iter().map(|((pruid, date), values)| {
+            let key = match by {
+                "pruid" => json!(pruid),
+                "date" => json!(date),
+                _ => Value::Null,
+            };
+            let value = match function {
+                "count" => Some(values.len() as f64),
+                "sum" => Some(values.iter().sum()),
+                "avg" if !values.is_empty() => Some(values.iter().sum::<f64>() / values.len() as f64),
+                "min" => values.iter().copied().reduce(f64::min),
+                "max" => values.iter().copied().reduce(f64::max),
+                _ => None,
+            };
+            json!({"key": key, "rows": values.len(), "value": value})
+        }).collect();
+        (200, json!({"column": column, "fn": function, "by": by, "groups": groups}))
+    }
+}
+
+/// A data row as a JSON object of its stored columns, with its id (`pruid/date`, the path of the row under
+/// `/rows`).
+pub fn row_json(row: &DataRow) -> Value {
+    json!({
+        "id": format!("{}/{}", row.pruid, row.date),
+        "pruid": row.pruid,
+        "prname": row.prname,
+        "prname_fr": row.prname_fr,
+        "date": row.date,
+        "numconf": row.numconf,
+        "numprob": row.numprob,
+        "numdeaths": row.numdeaths,
+        "numtotal": row.numtotal,
+        "numtoday": row.numtoday,
+        "ratetotal": row.ratetotal,
+    })
+}
+
+/// Decodes a URL query string component (`+` for spaces and `%XX` escapes).
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out: Vec<u8> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() => {
+                out.push(u8::from_str_radix(&s[i + 1..i + 3], 16).unwrap());
+                i += 2;
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use serde_json::json;
+
+    use crate::datastore::{DataRow, Header};
+    use crate::index::Index;
+    use crate::logic::Config;
+    use crate::rates::Population;
+    use crate::server::Table;
+
+    /// Runs a test on a table of two Ontario rows and a Quebec row that isn't saved after changes.
+    fn with_table(test: impl FnOnce(&mut Table)) {
+        with_config(Config::default(), false, test);
+    }
+
+    /// Runs a test on the same table with a config, saving it after each change if `persist` is set.
+    fn with_config(config: Config, persist: bool, test: impl FnOnce(&mut Table)) {
+        let column_labels = Header::new(["pruid", "prname", "prnameFR", "date", "numconf", "numprob", "numdeaths", "numtotal", "numtoday", "ratetotal"]
+                                            .iter().map(|l| l.to_string()).collect());
+        let population = Population::default();
+        let mut data = vec![DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "1", "10", "10", "0.1"]),
+                            DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-02", "12", "0", "1", "12", "2", "0.1"]),
+                            DataRow::new(vec!["24", "Quebec", "Québec", "2020-03-01", "4", "0", "0", "4", "4", "0.05"])];
+        let mut index = Index::new(&data);
+        let mut edited = BTreeSet::new();
+        test(&mut Table {config: &config, column_labels: &column_labels, population: &population,
+                         data: &mut data, index: &mut index, edited: &mut edited, stamp: &mut None, persist});
+    }
+
+    #[test]
+    fn test_list_rows() {
+        with_table(|table| {
+            let (status, body) = table.handle("GET", "/rows?filter=date%3D2020-03-01&sort=-numtoday&page_size=1&page=2", "");
+            assert_eq!(status, 200);
+            assert_eq!((body["total"].clone(), body["pages"].clone()), (json!(2), json!(2)));
+            assert_eq!(body["rows"][0]["id"], json!("24/2020-03-01"));
+        });
+    }
+
+    #[test]
+    fn test_list_parameters() {
+        with_table(|table| {
+            assert_eq!(table.handle("GET", "/rows?sort=colour", "").0, 400);
+            assert_eq!(table.handle("GET", "/rows?page=0", "").0, 400);
+            assert_eq!(table.handle("GET", "/rows?page_size=1001", "").0, 400);
+            assert_eq!(table.handle("GET", "/rows?filter=colour%3Dred", "").0, 400);
+
+            //A page far past the last one is empty rather than an overflow
+            let (status, body) = table.handle("GET", "/rows?page=18446744073709551615&page_size=2", "");
+            assert_eq!((status, body["rows"].clone(), body["pages"].clone()), (200, json!([]), json!(2)));
+        });
+    }
+
+    #[test]
+    fn test_row_ids() {
+        with_table(|table| {
+            assert_eq!(table.handle("GET", "/rows/35/2020-03-02", "").1["numtoday"], json!(2));
+            assert_eq!(table.handle("GET", "/rows/35/2020-03-09", "").0, 404);
+            assert_eq!(table.handle("GET", "/rows/1", "").0, 404);
+
+            //Deleting a row doesn't change the ids of the rows after it
+            assert_eq!(table.handle("DELETE", "/rows/35/2020-03-01", "").1["date"], json!("2020-03-01"));
+            assert_eq!(table.handle("GET", "/rows/35/2020-03-01", "").0, 404);
+            assert_eq!(table.handle("GET", "/rows/24/2020-03-01", "").1["prname_fr"], json!("Québec"));
+            assert_eq!(table.handle("PATCH", "/rows/24/2020-03-01", r#"{"numtoday": 5}"#).1["numtoday"], json!(5));
+        });
+    }
+
+    #[test]
+    fn test_change_rows() {
+        with_table(|table| {
+            let (status, body) = table.handle("PATCH", "/rows/35/2020-03-02", r#"{"numtoday": 3, "numconf": "lots"}"#);
+            assert_eq!((status, body["error"].clone()), (400, json!("numconf: \"lots\" is not a whole number")));
+            assert_eq!(table.handle("GET", "/rows/35/2020-03-02", "").1["numtoday"], json!(2));
+            assert_eq!(table.handle("PATCH", "/rows/35/2020-03-02", r#"{"numtoday": 3}"#).1["numtoday"], json!(3));
+
+            //Changing the date changes the id, and can't make two rows share one
+            assert_eq!(table.handle("PATCH", "/rows/35/2020-03-02", r#"{"date": "2020-03-01"}"#).0, 409);
+            assert_eq!(table.handle("PATCH", "/rows/35/2020-03-02", r#"{"date": "2020-03-03"}"#).1["id"], json!("35/2020-03-03"));
+        });
+    }
+
+    #[test]
+    fn test_send_back_row() {
+        with_table(|table| {
+            //A row from GET, changed and sent back whole, id included
+            let mut row = table.handle("GET", "/rows/35/2020-03-02", "").1;
+            row["numtoday"] = json!(3);
+            let (status, body) = table.handle("PATCH", "/rows/35/2020-03-02", &row.to_string());
+            assert_eq!((status, body.clone()), (200, row));
+
+            //The id doesn't choose the row a POST adds: its pruid and date do
+            let mut row = body;
+            row["date"] = json!("2020-03-05");
+            let (status, body) = table.handle("POST", "/rows", &row.to_string());
+            assert_eq!((status, body["id"].clone()), (201, json!("35/2020-03-05")));
+        });
+    }
+
+    #[test]
+    fn test_insert_rows() {
+        with_table(|table| {
+            let (status, body) = table.handle("POST", "/rows", r#"{"pruid": 24, "date": "2020-03-02", "numconf": 6, "numtotal": 6}"#);
+            assert_eq!((status, body["id"].clone(), body["prname"].clone()), (201, json!("24/2020-03-02"), json!("Quebec")));
+            assert_eq!(table.handle("POST", "/rows", r#"{"pruid": 24, "date": "2020-03-02"}"#).0, 409);
+            assert_eq!(table.handle("POST", "/rows", "not json").0, 400);
+            assert_eq!(table.handle("GET", "/rows/24/2020-03-02", "").1["numconf"], json!(6));
+        });
+    }
+
+    #[test]
+    fn test_save_failed() {
+        //The working file can't be saved in a folder that doesn't exist
+        let output = std::env::temp_dir().join(format!("tabledb-missing-{}", std::process::id())).join("datastore.csv");
+        let config = Config {output: output.to_str().unwrap().to_string(), ..Config::default()};
+        with_config(config, true, |table| {
+            let before = (table.data.clone(), table.index.clone(), table.edited.clone());
+            assert_eq!(table.handle("PATCH", "/rows/35/2020-03-02", r#"{"numtoday": 3}"#).0, 500);
+            assert_eq!(table.handle("POST", "/rows", r#"{"pruid": 24, "date": "2020-03-02", "numconf": 6}"#).0, 500);
+            assert_eq!(table.handle("DELETE", "/rows/24/2020-03-01", "").0, 500);
+
+            //Nothing that failed to save is left in memory for a later save to write
+            assert_eq!((table.data.clone(), table.index.clone(), table.edited.clone()), before);
+            assert_eq!(table.handle("GET", "/rows/35/2020-03-02", "").1["numtoday"], json!(2));
+        });
+    }
+
+    #[test]
+    fn test_aggregate() {
+        with_table(|table| {
+            let (_, body) = table.handle("GET", "/aggregate?column=numtotal&fn=max&by=pruid", "");
+            assert_eq!(body["groups"], json!([{"key": 24, "rows": 1, "value": 4.0}, {"key": 35, "rows": 2, "value": 12.0}]));
+            let (_, body) = table.handle("GET", "/aggregate?column=numtoday&filter=abbr%3DON", "");
+            assert_eq!(body["groups"], json!([{"key": null, "rows": 2, "value": 12.0}]));
+            assert_eq!(table.handle("GET", "/aggregate?fn=median", "").0, 400);
+            assert_eq!(table.handle("GET", "/aggregate?column=prname", "").0, 400);
+        });
+    }
+
+    #[test]
+    fn test_not_found() {
+        with_table(|table| {
+            assert_eq!(table.handle("PUT", "/rows/35/2020-03-01", "").0, 404);
+            assert_eq!(table.handle("GET", "/tables", "").0, 404);
+        });
+    }
+}
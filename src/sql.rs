@@ -0,0 +1,312 @@
+//Final Project - Thomas Ivanov
+
+use std::path::Path;
+
+use crate::datastore::DataRow;
+use crate::logic::Lang;
+use crate::messages::{self, Msg};
+use crate::ops;
+use crate::schema::{Column, Kind, SCHEMA};
+
+/// Rows written by each `INSERT` statement of a dump.
+pub const BATCH_SIZE: usize = 500;
+
+/// The table name for a dump file: its file name without the extension, with anything that isn't a letter,
+/// digit or underscore replaced so the name never needs quoting (`datastore` if nothing is left).
+pub fn table_name(filename: &str) -> String {
+    let stem = Path::new(filename).file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let name: String = stem.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' }).collect();
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name,
+        Some(_) => format!("t_{}", name),
+        None => String::from("datastore"),
+    }
+}
+
+/// The SQL type of a stored column, derived from its constraints in `schema::SCHEMA`.
+fn sql_type(column: &Column) -> String {
+    match column.kind {
+        Kind::Integer if column.max.is_some_and(|max| max <= i16::MAX as f64) => String::from("SMALLINT"),
+        Kind::Integer => String::from("INTEGER"),
+        Kind::Decimal => match (column.max, column.precision) {
+            (Some(max), Some(scale)) => format!("NUMERIC({},{})", (max.trunc() as i64).to_string().len() + scale, scale),
+            _ => String::from("DOUBLE PRECISION"),
+        },
+        Kind::Text => match column.max_len {
+            Some(len) => format!("VARCHAR({})", len),
+            None => String::from("TEXT"),
+        },
+        Kind::Date => String::from("DATE"),
+    }
+}
+
+/// Writes rows as an SQL script that creates a table named `table` and fills it, for SQLite or PostgreSQL.
+///
+/// The script runs in one transaction: it drops any table with the same name, creates it with a column
+/// for each stored column (typed and constrained as in `schema::SCHEMA`), inserts the rows `BATCH_SIZE`
+/// at a time, and indexes the table by `pruid` and `date`.
+///
+/// # Examples
+///
+/// ```
+/// fs::write("datastore.sql", dump("datastore", &data))?;
+/// ```
+/// ```
+/// my_project> sqlite3 covid.db < datastore.sql
+/// my_project> psql covid -f datastore.sql
+/// ```
+pub fn dump(table: &str, rows: &[DataRow]) -> String {
+    let mut s = format!("-- Table-DB export of {} rows\nBEGIN;\nDROP TABLE IF EXISTS {};\nCREATE TABLE {} (\n", rows.len(), table, table);
+    let columns: Vec<String> = SCHEMA.iter().map(|column| {
+        format!("    {} {}{}", column.name, sql_type(column), if column.required { " NOT NULL" } else { "" })
+    }).collect();
+    s.push_str(&columns.join(",\n"));
+    s.push_str("\n);\n");
+
+    let names: Vec<&str> = SCHEMA.iter().map(|column| column.name).collect();
+    for batch in rows.chunks(BATCH_SIZE) {
+        s.push_str(&format!("INSERT INTO {} ({}) VALUES\n", table, names.join(", ")));
+        let tuples: Vec<String> = batch.iter().map(|row| {
+            let values: Vec<String> = SCHEMA.iter().zip(row.public_vec()).map(|(column, value)| match column.kind {
+                Kind::Integer | Kind::Decimal => value,
+                Kind::Text | Kind::Date => format!("'{}'", value.replace('\'', "''")),
+            }).collect();
+            format!("({})", values.join(", "))
+        }).collect();
+        s.push_str(&tuples.join(",\n"));
+        s.push_str(";\n");
+    }
+    s.push_str(&format!("CREATE INDEX {}_key ON {} (pruid, date);\nCOMMIT;\n", table, table));
+    s
+}
+
+/// A piece of an SQL script, with the line it starts on.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// A keyword, name or number (double-quoted names are unquoted).
+    Word(String),
+    /// A single-quoted string, unquoted.
+    Text(String),
+    /// One of `(`, `)`, `,` and `;`.
+    Punct(char),
+}
+
+/// Splits an SQL script into tokens, skipping whitespace and `--` comments.
+fn tokenize(text: &str) -> Vec<(usize, Token)> {
+    let mut tokens: Vec<(usize, Token)> = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '-' if chars.peek() == Some(&'-') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            }
+            '(' | ')' | ',' | ';' => tokens.push((line, Token::Punct(c))),
+            '\'' | '"' => {
+                let start = line;
+                let mut s = String::new();
+                while let Some(d) = chars.next() {
+                    if d == c {
+                        //A quote is written twice inside a quoted string or name
+                        if chars.peek() == Some(&c) { chars.next(); } else { break; }
+                    }
+                    if d == '\n' {
+                        line += 1;
+                    }
+                    s.push(d);
+                }
+                tokens.push((start, if c == '\'' { Token::Text(s) } else { Token::Word(s) }));
+            }
+            c => {
+                let mut s = String::from(c);
+                while chars.peek().is_some_and(|d| !d.is_whitespace() && !"()',;\"".contains(*d)) {
+                    s.push(chars.next().unwrap());
+                }
+                tokens.push((line, Token::Word(s)));
+            }
+        }
+    }
+    tokens
+}
+
+/// Reads the rows of the `INSERT` statements of an SQL script, such as one written by `dump`, along with the
+/// line each row starts on. Each row holds a value for every column of `schema::SCHEMA`, in order (empty for
+/// `NULL` or a column the statement leaves out). Other statements are skipped. Returns the first problem
+/// found, described in the given language.
+pub fn parse(text: &str, lang: &Lang) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let tokens = tokenize(text);
+    let mut rows: Vec<(usize, Vec<String>)> = Vec::new();
+    for statement in tokens.split(|(_, t)| *t == Token::Punct(';')).filter(|s| !s.is_empty()) {
+        let is_word = |i: usize, word: &str| matches!(statement.get(i), Some((_, Token::Word(w))) if w.eq_ignore_ascii_case(word));
+        if !is_word(0, "insert") {
+            continue;
+        }
+        let mut parser = Parser {tokens: statement, pos: 1, lang};
+        parser.word("into")?;
+        parser.name()?;
+
+        //The column list maps the values to the stored columns
+        let mut columns: Vec<usize> = Vec::new();
+        parser.punct('(')?;
+        loop {
+            let (line, name) = (parser.line(), parser.name()?);
+            match ops::stored_column(&name) {
+                Some(c) if name.parse::<usize>().is_err() => columns.push(c),
+                _ => return Err(messages::fill(lang, Msg::SqlColumnUnknown, &[&line, &name])),
+            }
+            if parser.punct(')').is_ok() {
+                break;
+            }
+            parser.punct(',')?;
+        }
+
+        parser.word("values")?;
+        loop {
+            let start = parser.line();
+            parser.punct('(')?;
+            let mut values: Vec<String> = Vec::new();
+            loop {
+                values.push(parser.value()?);
+                if parser.punct(')').is_ok() {
+                    break;
+                }
+                parser.punct(',')?;
+            }
+            if values.len() != columns.len() {
+                return Err(messages::fill(lang, Msg::SqlValueCount, &[&start, &values.len(), &columns.len()]));
+            }
+            let mut fields = vec![String::new(); SCHEMA.len()];
+            for (c, value) in columns.iter().zip(values) {
+                fields[*c] = value;
+            }
+            rows.push((start, fields));
+            if parser.pos == statement.len() {
+                break;
+            }
+            parser.punct(',')?;
+        }
+    }
+    Ok(rows)
+}
+
+/// Reads the tokens of one statement in order.
+struct Parser<'a> {
+    tokens: &'a [(usize, Token)],
+    pos: usize,
+    lang: &'a Lang,
+}
+
+impl Parser<'_> {
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos).or(self.tokens.last()).map_or(0, |(line, _)| *line)
+    }
+
+    /// The error for the token at the current position.
+    fn error(&self) -> String {
+        let near = match self.tokens.get(self.pos) {
+            Some((_, Token::Word(w))) => w.clone(),
+            Some((_, Token::Text(t))) => format!("'{}'", t),
+            Some((_, Token::Punct(p))) => p.to_string(),
+            None => String::from(";"),
+        };
+        messages::fill(self.lang, Msg::SqlSyntax, &[&self.line(), &near])
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.pos).map(|(_, t)| t);
+        self.pos += 1;
+        token
+    }
+
+    fn expect<T>(&mut self, read: impl Fn(&Token) -> Option<T>) -> Result<T, String> {
+        match self.tokens.get(self.pos).and_then(|(_, t)| read(t)) {
+            Some(value) => {
+                self.next();
+                Ok(value)
+            }
+            None => Err(self.error()),
+        }
+    }
+
+    fn word(&mut self, word: &str) -> Result<(), String> {
+        self.expect(|t| match t {
+            Token::Word(w) if w.eq_ignore_ascii_case(word) => Some(()),
+            _ => None,
+        })
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        self.expect(|t| match t {
+            Token::Word(w) => Some(w.clone()),
+            _ => None,
+        })
+    }
+
+    fn punct(&mut self, c: char) -> Result<(), String> {
+        self.expect(|t| if *t == Token::Punct(c) { Some(()) } else { None })
+    }
+
+    /// A string, a number, or `NULL` (read as an empty value).
+    fn value(&mut self) -> Result<String, String> {
+        self.expect(|t| match t {
+            Token::Word(w) if w.eq_ignore_ascii_case("null") => Some(String::new()),
+            Token::Word(w) if w.parse::<f64>().is_ok() => Some(w.clone()),
+            Token::Text(s) => Some(s.clone()),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::datastore::DataRow;
+    use crate::logic::Lang;
+    use crate::sql;
+
+    fn rows() -> Vec<DataRow> {
+        vec![DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "1", "10", "10", "0.1"]),
+             DataRow::new(vec!["24", "Quebec", "Québec", "2020-03-02", "1234", "0", "0", "1234", "-2", "12.25"])]
+    }
+
+    #[test]
+    fn test_table_name() {
+        assert_eq!(sql::table_name("exports/Covid data.sql"), "covid_data");
+        assert_eq!(sql::table_name("2020.sql"), "t_2020");
+    }
+
+    #[test]
+    fn test_dump() {
+        let dump = sql::dump("datastore", &rows());
+        assert!(dump.contains("CREATE TABLE datastore (\n    pruid SMALLINT NOT NULL,\n    prname VARCHAR(30) NOT NULL,\n"));
+        assert!(dump.contains("    date DATE NOT NULL,\n") && dump.contains("    numdeaths INTEGER,\n") && dump.contains("    ratetotal NUMERIC(7,2)\n);"));
+        assert!(dump.contains("(24, 'Quebec', 'Québec', '2020-03-02', 1234, 0, 0, 1234, -2, 12.25);\n"));
+    }
+
+    #[test]
+    fn test_parse_dump() {
+        let rows = rows();
+        let read = sql::parse(&sql::dump("datastore", &rows), &Lang::EN).unwrap();
+        assert_eq!(read.iter().map(|(_, fields)| fields.clone()).collect::<Vec<Vec<String>>>(),
+                   rows.iter().map(|r| r.public_vec()).collect::<Vec<Vec<String>>>());
+        assert_eq!(read[1].0, read[0].0 + 1);
+    }
+
+    #[test]
+    fn test_parse_column_list() {
+        //Quotes are doubled inside strings, and columns can be left out or in any order
+        let read = sql::parse("insert into t (date, prname, numconf) values ('2020-03-01', 'O''Leary', NULL);", &Lang::EN).unwrap();
+        assert_eq!(read[0].1.len(), 10);
+        assert_eq!(read[0].1[..5], [String::new(), String::from("O'Leary"), String::new(), String::from("2020-03-01"), String::new()]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(sql::parse("INSERT INTO t (pruid, size) VALUES (1, 2);", &Lang::EN), Err(String::from("Line 1: there is no column size")));
+        assert_eq!(sql::parse("INSERT INTO t (pruid)\nVALUES (1, 2);", &Lang::EN), Err(String::from("Line 2: 2 values for 1 columns")));
+        assert_eq!(sql::parse("INSERT INTO t (pruid) VALUES (1) (2);", &Lang::EN), Err(String::from("Line 1: invalid SQL near \"(\"")));
+    }
+}
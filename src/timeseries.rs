@@ -0,0 +1,144 @@
+//Final Project - Thomas Ivanov
+
+use std::error::Error;
+use chrono::{Duration, NaiveDate};
+
+use crate::datastore::*;
+use crate::locale;
+use crate::logic::Config;
+use crate::messages::Msg;
+
+/// Labels for the derived columns, printed after the regular column labels in the time-series view.
+pub const SERIES_LABELS: [&str; 7] = ["dtotal", "ddeaths", "sum7", "avg7", "sum14", "avg14", "growth%"];
+
+/// A data row of one province's time series, along with the values derived from the rows before it.
+///
+/// The day-over-day deltas compare against the previous row in date order (`None` on a province's first
+/// row, where the cumulative counts have nothing to compare against), and the rolling sums and
+/// averages of `numtoday` cover the 7 and 14 calendar days ending on the row's date (so missing days
+/// count as zero). `growth` is the day-over-day change in `numtotal` as a percentage, and is `None`
+/// when there is no previous total to compare against.
+#[derive(Clone, Debug)]
+pub struct SeriesRow<'a> {
+    pub row: &'a DataRow,
+    pub delta_total: Option<isize>,
+    pub delta_deaths: Option<isize>,
+    pub sum_7: isize,
+    pub avg_7: f64,
+    pub sum_14: isize,
+    pub avg_14: f64,
+    pub growth: Option<f64>,
+}
+
+impl<'a> SeriesRow<'a> {
+    /// Produces the cells of the row in the current language, followed by the derived columns.
+    pub fn cells(&self, config: &Config) -> Vec<String> {
+        let lang = &config.language;
+        let mut cells = self.row.cells_lang(config);
+
+        cells.push(match self.delta_total {Some(d) => locale::format_int(d, lang), None => String::from("-")});
+        cells.push(match self.delta_deaths {Some(d) => locale::format_int(d, lang), None => String::from("-")});
+        cells.push(locale::format_int(self.sum_7, lang));
+        cells.push(locale::format_float(self.avg_7, 2, lang));
+        cells.push(locale::format_int(self.sum_14, lang));
+        cells.push(locale::format_float(self.avg_14, 2, lang));
+        cells.push(match self.growth {Some(g) => locale::format_float(g, 2, lang), None => String::from("-")});
+
+        cells
+    }
+}
+
+/// Produces the column labels for the time-series view in the current language.
+pub fn series_labels(column_labels: &Header, config: &Config) -> Vec<String> {
+    let mut labels = column_labels.labels_lang(config);
+    labels.extend(SERIES_LABELS.iter().map(|l| l.to_string()));
+    labels
+}
+
+/// Builds the time series for a set of data rows (normally all the rows of one province).
+///
+/// `positions` are indices into `data`, such as the ones returned by `Index::pruid`. The rows are put in
+/// date order before the derived values are computed. An error is returned if a row's date can't be parsed.
+///
+/// # Examples
+///
+/// ```
+/// let series = series(&data, &index.pruid(35), &config)?;
+/// println!("{:?}", series[0].cells(&config));
+/// ```
+pub fn series<'a>(data: &'a [DataRow], positions: &[usize], config: &Config) -> Result<Vec<SeriesRow<'a>>, Box<dyn Error>> {
+    let mut rows: Vec<(NaiveDate, &DataRow)> = Vec::new();
+    for &pos in positions {
+        let row = &data[pos];
+        let date = NaiveDate::parse_from_str(&row.date, "%Y-%m-%d")
+                        .map_err(|_| config.fill(Msg::SeriesDateInvalid, &[&pos, &row.date]))?;
+        rows.push((date, row));
+    }
+    rows.sort_by_key(|(date, _)| *date);
+
+    let mut out: Vec<SeriesRow> = Vec::new();
+    for (i, &(date, row)) in rows.iter().enumerate() {
+        let previous = if i > 0 { Some(rows[i-1].1) } else { None };
+        let sum_7 = window_sum(&rows[..=i], date, 7);
+        let sum_14 = window_sum(&rows[..=i], date, 14);
+
+        out.push(SeriesRow {
+            row,
+            delta_total: previous.map(|p| row.numtotal - p.numtotal),
+            delta_deaths: previous.map(|p| row.numdeaths - p.numdeaths),
+            sum_7,
+            avg_7: sum_7 as f64 / 7.0,
+            sum_14,
+            avg_14: sum_14 as f64 / 14.0,
+            growth: match previous {
+                Some(p) if p.numtotal != 0 => Some((row.numtotal - p.numtotal) as f64 / p.numtotal as f64 * 100.0),
+                _ => None,
+            },
+        });
+    }
+    Ok(out)
+}
+
+/// Sums `numtoday` over the rows dated within the `days` calendar days ending on `end`.
+/// The rows must be in date order and end with the row dated `end`.
+fn window_sum(rows: &[(NaiveDate, &DataRow)], end: NaiveDate, days: i64) -> isize {
+    let start = end - Duration::days(days - 1);
+    rows.iter()
+            .rev()
+            .take_while(|(date, _)| *date >= start)
+            .map(|(_, row)| row.numtoday)
+            .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::datastore::DataRow;
+    use crate::logic::{Config, Lang};
+    use crate::timeseries;
+
+    #[test]
+    fn test_series() {
+        //Rows are out of order and have a gap (2020-03-04 to 2020-03-09) to check calendar windows
+        let data = vec![
+            DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-02", "20", "0", "1", "20", "10", "0.1"]),
+            DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "0", "10", "10", "0.1"]),
+            DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-03", "50", "0", "1", "50", "30", "0.3"]),
+            DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-10", "60", "0", "2", "60", "10", "0.4"]),
+        ];
+        let config = Config {language: Lang::EN, filename: String::new(), ..Config::default()};
+        let series = timeseries::series(&data, &[0, 1, 2, 3], &config).unwrap();
+
+        assert_eq!(series[0].row, &data[1]);
+        assert_eq!(series[0].growth, None);
+        assert_eq!(series[0].delta_total, None);
+        assert_eq!(series[0].cells(&config)[9..11], ["-", "-"]);
+        assert_eq!(series[1].delta_total, Some(10));
+        assert_eq!(series[1].growth, Some(100.0));
+        assert_eq!(series[2].delta_deaths, Some(0));
+        assert_eq!(series[2].sum_7, 50);
+        //Only 2020-03-10 is within the 7 days ending on 2020-03-10, but all four are within 14
+        assert_eq!(series[3].sum_7, 10);
+        assert_eq!(series[3].sum_14, 60);
+        assert_eq!(series[3].avg_14, 60.0 / 14.0);
+    }
+}
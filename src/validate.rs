@@ -0,0 +1,163 @@
+//Final Project - Thomas Ivanov
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::datastore::DataRow;
+use crate::logic::Lang;
+use crate::messages::{self, Msg};
+
+/// The consistency rule broken by a `Violation`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule {
+    /// `numtotal` must equal `numconf + numprob`.
+    TotalSum,
+    /// `numconf`, `numdeaths` and `numtotal` must not go down over time for a `pruid`.
+    Cumulative,
+    /// `numtoday` must equal the change in `numtotal` since the previous date for a `pruid`.
+    DailyChange,
+    /// Every row of a `pruid` must have the same `prname` and `prname_fr`.
+    ProvinceName,
+}
+
+impl Rule {
+    /// The catalog message describing a violation of this rule.
+    fn message(&self) -> Msg {
+        match self {
+            Rule::TotalSum => Msg::RuleTotalSum,
+            Rule::Cumulative => Msg::RuleCumulative,
+            Rule::DailyChange => Msg::RuleDailyChange,
+            Rule::ProvinceName => Msg::RuleProvinceName,
+        }
+    }
+}
+
+/// A broken consistency rule, referring to the offending data row by its index in the data vector.
+///
+/// `args` fill in the placeholders of the rule's catalog message, so a violation can be described in
+/// either language with `describe`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub row: usize,
+    pub rule: Rule,
+    pub pruid: isize,
+    pub date: String,
+    pub args: Vec<String>,
+}
+
+impl Violation {
+    fn new(row: usize, rule: Rule, data: &DataRow, args: Vec<String>) -> Violation {
+        Violation {
+            row,
+            rule,
+            pruid: data.pruid,
+            date: data.date.clone(),
+            args,
+        }
+    }
+
+    /// Describes the violation in a language, e.g. "Row 4 (35 2020-03-02): numtotal 9 is not numconf 8 + numprob 0".
+    pub fn describe(&self, lang: &Lang) -> String {
+        let args: Vec<&dyn fmt::Display> = self.args.iter().map(|a| a as &dyn fmt::Display).collect();
+        let message = messages::fill(lang, self.rule.message(), &args);
+        messages::fill(lang, Msg::ViolationRow, &[&self.row, &self.pruid, &self.date, &message])
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.describe(&Lang::EN))
+    }
+}
+
+/// Checks the data rows against the invariants of the COVID dataset and returns every violation found.
+///
+/// Rows are grouped by `pruid` and put in date order for the checks that compare a row with the one
+/// before it. The returned violations are ordered by row index.
+///
+/// # Examples
+///
+/// ```
+/// for violation in validate(&data) {
+///     println!("{}", violation);
+/// }
+/// ```
+pub fn validate(data: &[DataRow]) -> Vec<Violation> {
+    let mut violations: Vec<Violation> = Vec::new();
+
+    for (i, row) in data.iter().enumerate() {
+        if row.numtotal != row.numconf + row.numprob {
+            violations.push(Violation::new(i, Rule::TotalSum, row,
+                                vec![row.numtotal.to_string(), row.numconf.to_string(), row.numprob.to_string()]));
+        }
+    }
+
+    //Group the rows by province, in date order
+    let mut provinces: HashMap<isize, Vec<usize>> = HashMap::new();
+    for (i, row) in data.iter().enumerate() {
+        provinces.entry(row.pruid).or_default().push(i);
+    }
+
+    for positions in provinces.values_mut() {
+        positions.sort_by(|a, b| data[*a].date.cmp(&data[*b].date).then(a.cmp(b)));
+        let first = &data[positions[0]];
+
+        let mut previous: Option<&DataRow> = None;
+        for &i in positions.iter() {
+            let row = &data[i];
+            if row.prname != first.prname || row.prname_fr != first.prname_fr {
+                violations.push(Violation::new(i, Rule::ProvinceName, row,
+                                    vec![row.prname.clone(), row.prname_fr.clone(), first.prname.clone(), first.prname_fr.clone(), row.pruid.to_string()]));
+            }
+
+            let before = previous.map_or(0, |p| p.numtotal);
+            if row.numtoday != row.numtotal - before {
+                violations.push(Violation::new(i, Rule::DailyChange, row,
+                                    vec![row.numtoday.to_string(), before.to_string(), row.numtotal.to_string()]));
+            }
+
+            if let Some(p) = previous {
+                for (column, now, then) in [("numconf", row.numconf, p.numconf), ("numdeaths", row.numdeaths, p.numdeaths), ("numtotal", row.numtotal, p.numtotal)] {
+                    if now < then {
+                        violations.push(Violation::new(i, Rule::Cumulative, row,
+                                            vec![column.to_string(), then.to_string(), p.date.clone(), now.to_string()]));
+                    }
+                }
+            }
+            previous = Some(row);
+        }
+    }
+
+    violations.sort_by_key(|v| v.row);
+    violations
+}
+
+#[cfg(test)]
+mod test {
+    use crate::datastore::DataRow;
+    use crate::logic::Lang;
+    use crate::validate::{self, Rule};
+
+    #[test]
+    fn test_validate() {
+        let data = vec![
+            DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-01", "10", "0", "1", "10", "10", "0.1"]),
+            DataRow::new(vec!["35", "Ontario", "Ontario", "2020-03-03", "8", "0", "0", "9", "-1", "0.1"]),
+            DataRow::new(vec!["35", "Ontaria", "Ontario", "2020-03-02", "12", "0", "1", "12", "5", "0.1"]),
+        ];
+        let violations = validate::validate(&data);
+        let rules: Vec<(usize, Rule)> = violations.iter().map(|v| (v.row, v.rule)).collect();
+
+        assert_eq!(rules, vec![
+            (1, Rule::TotalSum),
+            (1, Rule::DailyChange),
+            (1, Rule::Cumulative),
+            (1, Rule::Cumulative),
+            (1, Rule::Cumulative),
+            (2, Rule::ProvinceName),
+            (2, Rule::DailyChange),
+        ]);
+        assert_eq!(violations[0].describe(&Lang::EN), "Row 1 (35 2020-03-03): numtotal 9 is not numconf 8 + numprob 0");
+        assert_eq!(violations[0].describe(&Lang::FR), "Ligne 1 (35 2020-03-03) : numtotal 9 n'est pas numconf 8 + numprob 0");
+    }
+}
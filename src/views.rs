@@ -0,0 +1,141 @@
+//Final Project - Thomas Ivanov
+
+use std::collections::BTreeMap;
+
+/// Names of the columns of the display view in their default order. They are numbered from 1 for choosing
+/// and sorting (the province name column is `prname` in either language).
+pub const COLUMNS: [&str; 13] = ["pruid", "prname", "date", "numconf", "numprob", "numdeaths", "numtotal", "numtoday", "ratetotal",
+                                 "cases100k", "deaths100k", "cfr", "active"];
+
+/// Finds a display column by its name or number (1-13), returning its index in `COLUMNS`.
+pub fn column_index(column: &str) -> Option<usize> {
+    let column = column.trim().to_lowercase();
+    match column.parse::<usize>() {
+        Ok(n) if (1..=COLUMNS.len()).contains(&n) => Some(n - 1),
+        Ok(_) => None,
+        Err(_) if column == "prnamefr" || column == "prname_fr" => Some(1),
+        Err(_) => COLUMNS.iter().position(|c| *c == column),
+    }
+}
+
+/// Reads a comma-separated list of column names or numbers (or `*` for every column) into column indices.
+/// Returns the first entry that isn't a column if there is one.
+pub fn parse_columns(spec: &str) -> Result<Vec<usize>, String> {
+    if spec.trim() == "*" {
+        return Ok((0..COLUMNS.len()).collect());
+    }
+    let mut columns: Vec<usize> = Vec::new();
+    for entry in spec.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+        match column_index(entry) {
+            Some(c) if !columns.contains(&c) => columns.push(c),
+            Some(_) => {}
+            None => return Err(entry.to_string()),
+        }
+    }
+    if columns.is_empty() {
+        return Err(String::new());
+    }
+    Ok(columns)
+}
+
+/// Writes column indices back as the comma-separated list of names read by `parse_columns`.
+pub fn column_spec(columns: &[usize]) -> String {
+    columns.iter().map(|c| COLUMNS[*c]).collect::<Vec<&str>>().join(", ")
+}
+
+/// Returns true if a view name can be stored in the settings file and told apart from a list of columns.
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "*" && column_index(name).is_none()
+        && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The columns shown by `display`, in order, along with the named views saved by the user.
+///
+/// `current` holds indices into `COLUMNS`, and `name` is the saved view it came from (if any). Views are
+/// kept in the settings file (see `persistence::load_views`) so they can be reused across sessions, and
+/// their names are lowercased like the other keys of that file.
+///
+/// # Examples
+///
+/// ```
+/// let mut views = Views::default();
+/// views.select("pruid, date, numtoday")?;
+/// views.save("compact");
+/// let shown = views.pick(&cells);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Views {
+    pub saved: BTreeMap<String, Vec<usize>>,
+    pub current: Vec<usize>,
+    pub name: Option<String>,
+}
+
+impl Default for Views {
+    fn default() -> Views {
+        Views {
+            saved: BTreeMap::new(),
+            current: (0..COLUMNS.len()).collect(),
+            name: None,
+        }
+    }
+}
+
+impl Views {
+    /// Shows the columns of a saved view if `input` is the name of one, or else the columns listed in `input`.
+    pub fn select(&mut self, input: &str) -> Result<(), String> {
+        let input = input.trim();
+        let name = input.to_lowercase();
+        if let Some(columns) = self.saved.get(&name) {
+            self.current = columns.clone();
+            self.name = Some(name);
+            return Ok(());
+        }
+        self.current = parse_columns(input)?;
+        self.name = None;
+        Ok(())
+    }
+
+    /// Saves the columns shown as a named view, replacing any view with the same name.
+    pub fn save(&mut self, name: &str) {
+        let name = name.to_lowercase();
+        self.saved.insert(name.clone(), self.current.clone());
+        self.name = Some(name);
+    }
+
+    /// Picks the cells of the columns shown, in order, from the cells of every column.
+    pub fn pick<T: Clone>(&self, cells: &[T]) -> Vec<T> {
+        self.current.iter().filter_map(|c| cells.get(*c).cloned()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::views::{self, Views};
+
+    #[test]
+    fn test_views() {
+        assert_eq!(views::column_index("13"), Some(12));
+        assert_eq!(views::column_index("prnameFR"), Some(1));
+        assert_eq!(views::column_index("14"), None);
+        assert_eq!(views::parse_columns("pruid, 8, date, pruid"), Ok(vec![0, 7, 2]));
+        assert_eq!(views::parse_columns("pruid, numbers"), Err(String::from("numbers")));
+        assert_eq!(views::parse_columns(" , "), Err(String::new()));
+        assert_eq!(views::column_spec(&[0, 7, 2]), "pruid, numtoday, date");
+        assert!(views::valid_name("compact-2") && !views::valid_name("date") && !views::valid_name("a b"));
+
+        let mut views = Views::default();
+        let cells: Vec<usize> = (0..13).collect();
+        assert_eq!(views.pick(&cells), cells);
+
+        //Hide numprob and move numtoday next to date
+        views.select("pruid, prname, date, numtoday, numconf, numdeaths").unwrap();
+        views.save("compact");
+        assert_eq!(views.pick(&cells), vec![0, 1, 2, 7, 3, 5]);
+
+        views.select("*").unwrap();
+        assert_eq!(views.name, None);
+        views.select("Compact").unwrap();
+        assert_eq!((views.name.as_deref(), views.current.len()), (Some("compact"), 6));
+        assert!(views.select("nothing").is_err());
+    }
+}